@@ -18,6 +18,10 @@ fn get_named_arg_size(name: &str) -> Option<usize> {
     }
 }
 
+pub fn has_named_arg(name: &str) -> bool {
+    get_named_arg_size(name).is_some()
+}
+
 pub fn try_get_named_arg<T: FromBytes>(name: &str) -> Result<T, ApiError> {
     let arg_size = get_named_arg_size(name).ok_or(ApiError::MissingArgument)?;
     let arg_bytes = if arg_size > 0 {
@@ -0,0 +1,147 @@
+//! Emitting events according to the Casper Event Standard (CES).
+//!
+//! Events are stored in a dictionary of the emitting contract, keyed by their index. Each event is
+//! stored as the bytes of its name prefixed with `event_`, followed by the bytes of its fields.
+//! The schemas of all events the contract may emit are stored alongside, so that off-chain
+//! consumers can parse them.
+use alloc::{boxed::Box, collections::BTreeMap, format, string::ToString};
+use casper_types::{bytesrepr::Bytes, CLType, CLTyped};
+
+use crate::prelude::*;
+
+pub const EVENTS_DICT: &str = "__events";
+pub const EVENTS_LENGTH: &str = "__events_length";
+pub const EVENTS_SCHEMA: &str = "__events_schema";
+pub const CES_VERSION_KEY: &str = "__events_ces_version";
+pub const CES_VERSION: &str = "0.3.0";
+
+/// An event that can be emitted by a contract.
+pub trait Event: ToBytes {
+    const NAME: &'static str;
+
+    fn schema() -> Schema;
+}
+
+/// Names and types of the fields of an event, in the order they are serialized.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema(Vec<(String, CLType)>);
+
+impl Schema {
+    pub fn new(fields: Vec<(String, CLType)>) -> Self {
+        Self(fields)
+    }
+}
+
+impl ToBytes for Schema {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        self.0.to_bytes()
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.0.serialized_length()
+    }
+}
+
+impl FromBytes for Schema {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (fields, bytes) = Vec::<(String, CLType)>::from_bytes(bytes)?;
+        Ok((Self(fields), bytes))
+    }
+}
+
+impl CLTyped for Schema {
+    fn cl_type() -> CLType {
+        CLType::List(Box::new(CLType::Tuple2([
+            Box::new(CLType::String),
+            Box::new(CLType::Any),
+        ])))
+    }
+}
+
+/// Schemas of all the events a contract may emit, by event name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schemas(BTreeMap<String, Schema>);
+
+impl Schemas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<T: Event>(mut self) -> Self {
+        self.0.insert(T::NAME.to_string(), T::schema());
+        self
+    }
+}
+
+impl ToBytes for Schemas {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        self.0.to_bytes()
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.0.serialized_length()
+    }
+}
+
+impl FromBytes for Schemas {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (schemas, bytes) = BTreeMap::<String, Schema>::from_bytes(bytes)?;
+        Ok((Self(schemas), bytes))
+    }
+}
+
+impl CLTyped for Schemas {
+    fn cl_type() -> CLType {
+        BTreeMap::<String, Schema>::cl_type()
+    }
+}
+
+/// Create the named keys needed to emit events. They must be added to the named keys of the
+/// contract that emits the events.
+pub fn init(schemas: Schemas) -> Vec<(String, Key)> {
+    let events = crate::contract_api::new_dictionary_anon();
+    let events_length = storage::new_uref(0u32);
+    let events_schema = storage::new_uref(schemas);
+    let ces_version = storage::new_uref(CES_VERSION.to_string());
+
+    vec![
+        (EVENTS_DICT.to_string(), events.into()),
+        (EVENTS_LENGTH.to_string(), events_length.into()),
+        (EVENTS_SCHEMA.to_string(), events_schema.into()),
+        (CES_VERSION_KEY.to_string(), ces_version.into()),
+    ]
+}
+
+/// Set up events in a contract that was installed without them, or update the stored schemas of a
+/// contract that already emits events. Must be called from the context of the contract.
+pub fn upgrade(schemas: Schemas) {
+    match runtime::get_key(EVENTS_SCHEMA) {
+        Some(_) => storage::write(named_uref(EVENTS_SCHEMA), schemas),
+        None => {
+            for (name, key) in init(schemas) {
+                runtime::put_key(&name, key);
+            }
+        }
+    }
+}
+
+/// Emit an event from the current contract.
+pub fn emit<T: Event>(event: &T) {
+    let events = named_uref(EVENTS_DICT);
+    let events_length = named_uref(EVENTS_LENGTH);
+    let index: u32 = crate::o_unwrap!(
+        crate::r_unwrap!(storage::read(events_length), ApiError::Deserialize),
+        ApiError::MissingKey
+    );
+
+    let mut bytes = crate::r_unwrap!(format!("event_{}", T::NAME).to_bytes());
+    bytes.append(&mut crate::r_unwrap!(event.to_bytes()));
+
+    storage::dictionary_put(events, &index.to_string(), Bytes::from(bytes));
+    storage::write(events_length, index + 1);
+}
+
+fn named_uref(name: &str) -> URef {
+    let key = crate::o_unwrap!(runtime::get_key(name), ApiError::MissingKey);
+    crate::o_unwrap!(key.into_uref(), ApiError::UnexpectedKeyVariant)
+}
@@ -120,53 +120,61 @@ pub mod cep82 {
 
         use crate::{named_arg, token::TokenIdentifier, trace_block};
 
-        pub fn can_transfer(
+        pub fn calculate_royalty(
             package: ContractPackageHash,
+            token_contract: ContractPackageHash,
             token_id: &TokenIdentifier,
+            payment_amount: U512,
             source_key: Key,
-            target_key: Key,
-        ) {
+        ) -> U512 {
             trace_block! {{
-                runtime::call_versioned_contract::<()>(
+                runtime::call_versioned_contract::<U512>(
                     package,
                     None,
-                    "can_transfer",
+                    "calculate_royalty",
                     vec![
+                        named_arg!(token_contract),
                         token_id.to_named_arg(),
+                        named_arg!(payment_amount),
                         named_arg!(source_key),
-                        named_arg!(target_key),
-                    ]
-                    .into(),
-                );
+                    ].into(),
+                )
             }}
         }
 
-        pub fn calculate_royalty(
+        #[allow(clippy::too_many_arguments)]
+        pub fn pay_royalty(
             package: ContractPackageHash,
             token_contract: ContractPackageHash,
             token_id: &TokenIdentifier,
+            source_purse: URef,
+            payer: Key,
+            source_key: Key,
+            target_key: Key,
             payment_amount: U512,
-        ) -> U512 {
+        ) {
             trace_block! {{
-                runtime::call_versioned_contract::<U512>(
+                runtime::call_versioned_contract::<()>(
                     package,
                     None,
-                    "calculate_royalty",
+                    "pay_royalty",
                     vec![
                         named_arg!(token_contract),
                         token_id.to_named_arg(),
+                        named_arg!(source_purse),
+                        named_arg!(payer),
+                        named_arg!(source_key),
+                        named_arg!(target_key),
                         named_arg!(payment_amount),
                     ].into(),
                 )
             }}
         }
 
-        #[allow(clippy::too_many_arguments)]
-        pub fn pay_royalty(
+        pub fn pay_royalty_cep18(
             package: ContractPackageHash,
             token_contract: ContractPackageHash,
             token_id: &TokenIdentifier,
-            source_purse: URef,
             payer: Key,
             source_key: Key,
             target_key: Key,
@@ -176,11 +184,10 @@ pub mod cep82 {
                 runtime::call_versioned_contract::<()>(
                     package,
                     None,
-                    "pay_royalty",
+                    "pay_royalty_cep18",
                     vec![
                         named_arg!(token_contract),
                         token_id.to_named_arg(),
-                        named_arg!(source_purse),
                         named_arg!(payer),
                         named_arg!(source_key),
                         named_arg!(target_key),
@@ -189,5 +196,47 @@ pub mod cep82 {
                 )
             }}
         }
+
+        pub fn get_royalty_currency(
+            package: ContractPackageHash,
+            token_contract: ContractPackageHash,
+            token_id: &TokenIdentifier,
+            source_key: Key,
+        ) -> Option<ContractPackageHash> {
+            trace_block! {{
+                runtime::call_versioned_contract::<Option<ContractPackageHash>>(
+                    package,
+                    None,
+                    "get_royalty_currency",
+                    vec![
+                        named_arg!(token_contract),
+                        token_id.to_named_arg(),
+                        named_arg!(source_key),
+                    ].into(),
+                )
+            }}
+        }
+
+        pub fn is_transfer_authorized(
+            package: ContractPackageHash,
+            token_contract: ContractPackageHash,
+            token_id: &TokenIdentifier,
+            source_key: Key,
+            target_key: Key,
+        ) -> bool {
+            trace_block! {{
+                runtime::call_versioned_contract::<bool>(
+                    package,
+                    None,
+                    "is_transfer_authorized",
+                    vec![
+                        named_arg!(token_contract),
+                        token_id.to_named_arg(),
+                        named_arg!(source_key),
+                        named_arg!(target_key),
+                    ].into(),
+                )
+            }}
+        }
     }
 }
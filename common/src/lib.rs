@@ -1,9 +1,9 @@
 #![no_std]
 
 use alloc::{format, string::{String, ToString}, vec::Vec};
-use casper_contract::contract_api::runtime;
+use casper_contract::{contract_api::runtime, unwrap_or_revert::UnwrapOrRevert};
 use casper_types::{
-    bytesrepr::FromBytes, ContractPackageHash, Key, URef
+    bytesrepr::{FromBytes, ToBytes}, CLType, CLTyped, ContractPackageHash, Key, URef
 };
 use token::TokenIdentifier;
 
@@ -31,13 +31,14 @@ pub mod prelude {
     pub use crate::{
         contract_api::try_get_named_arg, ensure, ensure_eq, ensure_neq, entrypoint,
         entrypoints, error::CommonError, forward_entrypoints, named_arg, named_key, named_keys,
-        serializable_structs, st_non_sync_static,
+        serializable_events, serializable_structs, st_non_sync_static,
     };
 }
 
 pub mod call_stack;
 pub mod contract_api;
 pub mod error;
+pub mod events;
 pub mod ext;
 pub mod macros;
 pub mod token;
@@ -61,6 +62,26 @@ impl ToStrKey for TokenIdentifier {
     }
 }
 
+// Formatted keys are longer than the 64 bytes allowed for a dictionary item key, so keys and
+// compound values are serialized and hashed instead.
+fn hashed_key(bytes: Vec<u8>) -> String {
+    hex::encode(runtime::blake2b(bytes))
+}
+
+impl ToStrKey for Key {
+    fn to_key(&self) -> String {
+        hashed_key(self.to_bytes().unwrap_or_revert())
+    }
+}
+
+impl<A: ToBytes, B: ToBytes> ToStrKey for (A, B) {
+    fn to_key(&self) -> String {
+        let mut bytes = self.0.to_bytes().unwrap_or_revert();
+        bytes.append(&mut self.1.to_bytes().unwrap_or_revert());
+        hashed_key(bytes)
+    }
+}
+
 impl ToStrKey for u64 {
     fn to_key(&self) -> String {
         format!("{self}")
@@ -89,6 +110,14 @@ pub trait FromNamedArg {
     {
         crate::contract_api::try_get_named_arg(name).ok()
     }
+
+    /// The type of the argument as declared by entry points.
+    fn arg_cl_type() -> CLType
+    where
+        Self: CLTyped,
+    {
+        Self::cl_type()
+    }
 }
 
 macro_rules! impl_from_named_arg {
@@ -122,8 +151,21 @@ impl<T: FromBytes> FromNamedArg for Vec<T> {
     }
 }
 
-impl<T: FromNamedArg + FromBytes> FromNamedArg for Option<T> {
+/// Optional arguments are passed as their inner type, or omitted for `None`. Entry points declare
+/// them with their inner type accordingly. An argument that is passed but cannot be parsed reverts,
+/// rather than passing for an omitted one.
+impl<T: FromNamedArg + FromBytes + CLTyped> FromNamedArg for Option<T> {
     fn try_get(name: &str) -> Option<Self> {
-        Some(T::try_get(name))
+        match T::try_get(name) {
+            Some(value) => Some(Some(value)),
+            None if crate::contract_api::has_named_arg(name) => {
+                runtime::revert(casper_types::ApiError::InvalidArgument)
+            }
+            None => Some(None),
+        }
+    }
+
+    fn arg_cl_type() -> CLType {
+        T::arg_cl_type()
     }
 }
@@ -251,18 +251,65 @@ macro_rules! serializable_structs {
     };
 }
 
+/// Helper macro that declares serializable event structs, implementing
+/// [`Event`](crate::events::Event) for each of them.
+#[macro_export]
+macro_rules! serializable_events {
+    (
+        $( $(#[$meta:meta])* $sv:vis struct $name:ident {
+            $( $fv:vis $field:ident : $t:ty ),*
+            $(,)?
+        })+
+    ) => {
+        $crate::serializable_structs! {
+            $(
+                $(#[$meta])*
+                $sv struct $name {
+                    $( $fv $field : $t ),*
+                }
+            )+
+        }
+
+        $(
+            impl $crate::events::Event for $name {
+                const NAME: &'static str = stringify!($name);
+
+                fn schema() -> $crate::events::Schema {
+                    use casper_types::CLTyped;
+
+                    $crate::events::Schema::new(alloc::vec![
+                        $(
+                            (alloc::string::ToString::to_string(stringify!($field)), <$t>::cl_type()),
+                        )*
+                    ])
+                }
+            }
+        )+
+    };
+}
+
 /// Declare an entrypoint signature for a contract.
+///
+/// The markers of an entrypoint give its access, either `public` or `group(label)` to restrict it
+/// to the members of a user group of the contract package, followed by its context, either
+/// `contract` or `session`.
 #[allow(clippy::crate_in_macro_def)]
 #[macro_export]
 macro_rules! entrypoint {
     (
-        @access public $($attrib:ident)*
+        @access public $($attrib:tt)*
     ) => {
         casper_types::EntryPointAccess::Public
     };
 
     (
-        @access $_:ident $($attrib:ident)*
+        @access group ( $group:expr ) $($attrib:tt)*
+    ) => {
+        casper_types::EntryPointAccess::Groups(alloc::vec![casper_types::Group::new($group)])
+    };
+
+    (
+        @access $_:tt $($attrib:tt)*
     ) => {
         $crate::entrypoint! { @access $($attrib)* }
     };
@@ -274,19 +321,19 @@ macro_rules! entrypoint {
     };
 
     (
-        @context contract $($attrib:ident)*
+        @context contract $($attrib:tt)*
     ) => {
         casper_types::EntryPointType::Contract
     };
 
     (
-        @context session $($attrib:ident)*
+        @context session $($attrib:tt)*
     ) => {
         casper_types::EntryPointType::Session
     };
 
     (
-        @context $_:ident $($attrib:ident)*
+        @context $_:tt $($attrib:tt)*
     ) => {
         $crate::entrypoint! { @context $($attrib)* }
     };
@@ -323,7 +370,7 @@ macro_rules! entrypoint {
 
     (
         $(#[$meta:meta])*
-        [$($marker:ident)*] fn $name:ident
+        [$($marker:tt)*] fn $name:ident
         ( $( $arg:ident : $t:ty ),* $(,)?) -> $ret:ty = $callback:path
     ) => {
         $(#[$meta])*
@@ -358,7 +405,10 @@ macro_rules! entrypoint {
                     stringify!($name),
                     alloc::vec![
                         $(
-                            casper_types::Parameter::new(stringify!($arg), <$t>::cl_type()),
+                            casper_types::Parameter::new(
+                                stringify!($arg),
+                                <$t as $crate::FromNamedArg>::arg_cl_type(),
+                            ),
                         )*
                     ],
                     <$ret>::cl_type(),
@@ -377,7 +427,7 @@ macro_rules! entrypoints {
     (
         $(
             $(#[$meta:meta])*
-            $([$($marker:ident)*])? fn $name:ident
+            $([$($marker:tt)*])? fn $name:ident
             ( $( $arg:ident : $t:ty ),* $(,)?) -> $ret:ty = $callback:path
         );* $(;)?
     ) => {
@@ -1,11 +1,15 @@
 use alloc::vec;
 use alloc::vec::Vec;
 use casper_types::{
-    bytesrepr::{self, FromBytes, ToBytes},
-    CLTyped, Key, U256, U512,
+    bytesrepr::{self, Bytes, FromBytes, ToBytes, U32_SERIALIZED_LENGTH},
+    CLTyped, ContractPackageHash, Key, U256, U512,
 };
 
-use crate::state::{RoyaltyPaymentState, RoyaltyStep};
+use crate::state::{
+    LegacyRoyaltyPaymentState, LegacyRoyaltyStructure, RoyaltyPaymentState, RoyaltyQuote,
+    RoyaltyRecipient, RoyaltyStats, RoyaltyStep, RoyaltyStructure, RoyaltyWindow, SettledPayment,
+    Withdrawal,
+};
 
 const ROYALTY_PAYMENT_STATE_PAID: u8 = 1;
 const ROYALTY_PAYMENT_STATE_UNPAID: u8 = 0;
@@ -16,13 +20,25 @@ impl ToBytes for RoyaltyPaymentState {
             Self::Paid {
                 payer,
                 source_key,
+                target_key,
+                marketplace,
+                sale_price,
                 amount,
+                currency,
+                paid_at,
+                sale,
             } => {
                 let mut result = Vec::with_capacity(self.serialized_length());
                 result.push(ROYALTY_PAYMENT_STATE_PAID);
                 result.append(&mut payer.to_bytes()?);
                 result.append(&mut source_key.to_bytes()?);
+                result.append(&mut target_key.to_bytes()?);
+                result.append(&mut marketplace.to_bytes()?);
+                result.append(&mut sale_price.to_bytes()?);
                 result.append(&mut amount.to_bytes()?);
+                result.append(&mut currency.to_bytes()?);
+                result.append(&mut paid_at.to_bytes()?);
+                result.append(&mut sale.to_bytes()?);
                 Ok(result)
             }
             Self::Unpaid => Ok(vec![ROYALTY_PAYMENT_STATE_UNPAID]),
@@ -34,11 +50,23 @@ impl ToBytes for RoyaltyPaymentState {
             Self::Paid {
                 payer,
                 source_key,
+                target_key,
+                marketplace,
+                sale_price,
                 amount,
+                currency,
+                paid_at,
+                sale,
             } => {
                 1 + payer.serialized_length()
                     + source_key.serialized_length()
+                    + target_key.serialized_length()
+                    + marketplace.serialized_length()
+                    + sale_price.serialized_length()
                     + amount.serialized_length()
+                    + currency.serialized_length()
+                    + paid_at.serialized_length()
+                    + sale.serialized_length()
             }
             Self::Unpaid => 1,
         }
@@ -52,12 +80,24 @@ impl FromBytes for RoyaltyPaymentState {
             ROYALTY_PAYMENT_STATE_PAID => {
                 let (payer, bytes) = Key::from_bytes(bytes)?;
                 let (source_key, bytes) = Key::from_bytes(bytes)?;
+                let (target_key, bytes) = Key::from_bytes(bytes)?;
+                let (marketplace, bytes) = Key::from_bytes(bytes)?;
+                let (sale_price, bytes) = U512::from_bytes(bytes)?;
                 let (amount, bytes) = U512::from_bytes(bytes)?;
+                let (currency, bytes) = Option::<ContractPackageHash>::from_bytes(bytes)?;
+                let (paid_at, bytes) = u64::from_bytes(bytes)?;
+                let (sale, bytes) = bool::from_bytes(bytes)?;
                 Ok((
                     Self::Paid {
                         payer,
                         source_key,
+                        target_key,
+                        marketplace,
+                        sale_price,
                         amount,
+                        currency,
+                        paid_at,
+                        sale,
                     },
                     bytes,
                 ))
@@ -68,47 +108,160 @@ impl FromBytes for RoyaltyPaymentState {
     }
 }
 
-const ROYALTY_STEP_MINIMUM: u8 = 0;
-const ROYALTY_STEP_FLAT: u8 = 1;
-const ROYALTY_STEP_PERCENTAGE: u8 = 2;
-
-impl ToBytes for RoyaltyStep {
+// Only needed to clear legacy entries, which must keep their untyped layout.
+impl ToBytes for LegacyRoyaltyPaymentState {
     fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
-        match self {
-            Self::Minimum { amount } => {
+        match &self.0 {
+            RoyaltyPaymentState::Paid {
+                payer,
+                source_key,
+                amount,
+                ..
+            } => {
                 let mut result = Vec::with_capacity(self.serialized_length());
-                result.push(ROYALTY_STEP_MINIMUM);
+                result.push(ROYALTY_PAYMENT_STATE_PAID);
+                result.append(&mut payer.to_bytes()?);
+                result.append(&mut source_key.to_bytes()?);
                 result.append(&mut amount.to_bytes()?);
                 Ok(result)
             }
-            Self::Flat { amount } => {
-                let mut result = Vec::with_capacity(self.serialized_length());
-                result.push(ROYALTY_STEP_FLAT);
+            RoyaltyPaymentState::Unpaid => Ok(vec![ROYALTY_PAYMENT_STATE_UNPAID]),
+        }
+    }
+
+    fn serialized_length(&self) -> usize {
+        match &self.0 {
+            RoyaltyPaymentState::Paid {
+                payer,
+                source_key,
+                amount,
+                ..
+            } => {
+                1 + payer.serialized_length()
+                    + source_key.serialized_length()
+                    + amount.serialized_length()
+            }
+            RoyaltyPaymentState::Unpaid => 1,
+        }
+    }
+}
+
+impl FromBytes for LegacyRoyaltyPaymentState {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (state, bytes) = u8::from_bytes(bytes)?;
+        match state {
+            ROYALTY_PAYMENT_STATE_PAID => {
+                let (payer, bytes) = Key::from_bytes(bytes)?;
+                let (source_key, bytes) = Key::from_bytes(bytes)?;
+                let (amount, bytes) = U512::from_bytes(bytes)?;
+                Ok((
+                    Self(RoyaltyPaymentState::Paid {
+                        payer,
+                        source_key,
+                        target_key: source_key,
+                        marketplace: payer,
+                        sale_price: U512::zero(),
+                        amount,
+                        currency: None,
+                        paid_at: 0,
+                        sale: false,
+                    }),
+                    bytes,
+                ))
+            }
+            ROYALTY_PAYMENT_STATE_UNPAID => Ok((Self(RoyaltyPaymentState::Unpaid), bytes)),
+            _ => Err(bytesrepr::Error::Formatting),
+        }
+    }
+}
+
+impl FromBytes for LegacyRoyaltyStructure {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (steps, bytes) = Vec::<RoyaltyStep>::from_bytes(bytes)?;
+        Ok((
+            Self(RoyaltyStructure {
+                steps,
+                recipients: Vec::new(),
+                currency: None,
+            }),
+            bytes,
+        ))
+    }
+}
+
+const ROYALTY_STEP_MINIMUM: u8 = 0;
+const ROYALTY_STEP_FLAT: u8 = 1;
+const ROYALTY_STEP_PERCENTAGE: u8 = 2;
+const ROYALTY_STEP_BRACKET: u8 = 3;
+const ROYALTY_STEP_MAXIMUM: u8 = 4;
+const ROYALTY_STEP_SCHEDULED: u8 = 5;
+const ROYALTY_STEP_PROFIT_SHARE: u8 = 6;
+
+// Steps are laid out as their tag followed by their fields as `Bytes`, so that every step has the
+// same CL type. Their tag has this bit set, unlike the tag of steps stored before, which is
+// directly followed by their fields.
+const ROYALTY_STEP_FIELDS: u8 = 0x80;
+
+impl RoyaltyStep {
+    fn tag(&self) -> u8 {
+        match self {
+            Self::Minimum { .. } => ROYALTY_STEP_MINIMUM,
+            Self::Flat { .. } => ROYALTY_STEP_FLAT,
+            Self::Percentage { .. } => ROYALTY_STEP_PERCENTAGE,
+            Self::Bracket { .. } => ROYALTY_STEP_BRACKET,
+            Self::Maximum { .. } => ROYALTY_STEP_MAXIMUM,
+            Self::Scheduled { .. } => ROYALTY_STEP_SCHEDULED,
+            Self::ProfitShare { .. } => ROYALTY_STEP_PROFIT_SHARE,
+        }
+    }
+
+    fn fields_to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = Vec::with_capacity(self.fields_serialized_length());
+        match self {
+            Self::Minimum { amount } | Self::Flat { amount } | Self::Maximum { amount } => {
                 result.append(&mut amount.to_bytes()?);
-                Ok(result)
             }
             Self::Percentage { percent } => {
-                let mut result = Vec::with_capacity(self.serialized_length());
-                result.push(ROYALTY_STEP_PERCENTAGE);
                 result.append(&mut percent.to_bytes()?);
-                Ok(result)
+            }
+            Self::Bracket { from, to, percent } => {
+                result.append(&mut from.to_bytes()?);
+                result.append(&mut to.to_bytes()?);
+                result.append(&mut percent.to_bytes()?);
+            }
+            Self::Scheduled { windows } => {
+                result.append(&mut windows.to_bytes()?);
+            }
+            Self::ProfitShare {
+                percent,
+                fallback_percent,
+            } => {
+                result.append(&mut percent.to_bytes()?);
+                result.append(&mut fallback_percent.to_bytes()?);
             }
         }
+        Ok(result)
     }
 
-    fn serialized_length(&self) -> usize {
+    fn fields_serialized_length(&self) -> usize {
         match self {
-            Self::Minimum { amount } => 1 + amount.serialized_length(),
-            Self::Flat { amount } => 1 + amount.serialized_length(),
-            Self::Percentage { percent } => 1 + percent.serialized_length(),
+            Self::Minimum { amount } | Self::Flat { amount } | Self::Maximum { amount } => {
+                amount.serialized_length()
+            }
+            Self::Percentage { percent } => percent.serialized_length(),
+            Self::Bracket { from, to, percent } => {
+                from.serialized_length() + to.serialized_length() + percent.serialized_length()
+            }
+            Self::Scheduled { windows } => windows.serialized_length(),
+            Self::ProfitShare {
+                percent,
+                fallback_percent,
+            } => percent.serialized_length() + fallback_percent.serialized_length(),
         }
     }
-}
 
-impl FromBytes for RoyaltyStep {
-    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
-        let (step, bytes) = u8::from_bytes(bytes)?;
-        match step {
+    fn fields_from_bytes(tag: u8, bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        match tag {
             ROYALTY_STEP_MINIMUM => {
                 let (amount, bytes) = U512::from_bytes(bytes)?;
                 Ok((Self::Minimum { amount }, bytes))
@@ -121,12 +274,363 @@ impl FromBytes for RoyaltyStep {
                 let (percent, bytes) = U256::from_bytes(bytes)?;
                 Ok((Self::Percentage { percent }, bytes))
             }
+            ROYALTY_STEP_BRACKET => {
+                let (from, bytes) = U512::from_bytes(bytes)?;
+                let (to, bytes) = Option::<U512>::from_bytes(bytes)?;
+                let (percent, bytes) = U256::from_bytes(bytes)?;
+                Ok((Self::Bracket { from, to, percent }, bytes))
+            }
+            ROYALTY_STEP_MAXIMUM => {
+                let (amount, bytes) = U512::from_bytes(bytes)?;
+                Ok((Self::Maximum { amount }, bytes))
+            }
+            ROYALTY_STEP_SCHEDULED => {
+                let (windows, bytes) = Vec::<RoyaltyWindow>::from_bytes(bytes)?;
+                Ok((Self::Scheduled { windows }, bytes))
+            }
+            ROYALTY_STEP_PROFIT_SHARE => {
+                let (percent, bytes) = U256::from_bytes(bytes)?;
+                let (fallback_percent, bytes) = U256::from_bytes(bytes)?;
+                Ok((
+                    Self::ProfitShare {
+                        percent,
+                        fallback_percent,
+                    },
+                    bytes,
+                ))
+            }
             _ => Err(bytesrepr::Error::Formatting),
         }
     }
 }
 
+impl ToBytes for RoyaltyStep {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = Vec::with_capacity(self.serialized_length());
+        result.push(self.tag() | ROYALTY_STEP_FIELDS);
+        result.append(&mut Bytes::from(self.fields_to_bytes()?).to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        1 + U32_SERIALIZED_LENGTH + self.fields_serialized_length()
+    }
+}
+
+impl FromBytes for RoyaltyStep {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (tag, bytes) = u8::from_bytes(bytes)?;
+        if tag & ROYALTY_STEP_FIELDS == 0 {
+            return Self::fields_from_bytes(tag, bytes);
+        }
+
+        let (fields, bytes) = Bytes::from_bytes(bytes)?;
+        let (step, remainder) = Self::fields_from_bytes(tag & !ROYALTY_STEP_FIELDS, &fields)?;
+        if !remainder.is_empty() {
+            return Err(bytesrepr::Error::LeftOverBytes);
+        }
+
+        Ok((step, bytes))
+    }
+}
+
+// The fields of a step are those of its variant, in order, and its tag is the sum of
+// `ROYALTY_STEP_FIELDS` and the tag of its variant. Windows of a scheduled step are laid out like
+// `(u64, Option<u64>, U256)`.
+impl CLTyped for RoyaltyStep {
+    fn cl_type() -> casper_types::CLType {
+        <(u8, Bytes)>::cl_type()
+    }
+}
+
+impl ToBytes for RoyaltyRecipient {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = Vec::with_capacity(self.serialized_length());
+        result.append(&mut self.recipient.to_bytes()?);
+        result.append(&mut self.share.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.recipient.serialized_length() + self.share.serialized_length()
+    }
+}
+
+impl FromBytes for RoyaltyRecipient {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (recipient, bytes) = Key::from_bytes(bytes)?;
+        let (share, bytes) = u32::from_bytes(bytes)?;
+        Ok((Self { recipient, share }, bytes))
+    }
+}
+
+impl CLTyped for RoyaltyRecipient {
+    fn cl_type() -> casper_types::CLType {
+        <(Key, u32)>::cl_type()
+    }
+}
+
+impl ToBytes for RoyaltyStructure {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = Vec::with_capacity(self.serialized_length());
+        result.append(&mut self.steps.to_bytes()?);
+        result.append(&mut self.recipients.to_bytes()?);
+        result.append(&mut self.currency.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.steps.serialized_length()
+            + self.recipients.serialized_length()
+            + self.currency.serialized_length()
+    }
+}
+
+impl FromBytes for RoyaltyStructure {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (steps, bytes) = Vec::<RoyaltyStep>::from_bytes(bytes)?;
+        let (recipients, bytes) = Vec::<RoyaltyRecipient>::from_bytes(bytes)?;
+        let (currency, bytes) = Option::<ContractPackageHash>::from_bytes(bytes)?;
+        Ok((
+            Self {
+                steps,
+                recipients,
+                currency,
+            },
+            bytes,
+        ))
+    }
+}
+
+impl CLTyped for RoyaltyStructure {
+    fn cl_type() -> casper_types::CLType {
+        <(
+            Vec<RoyaltyStep>,
+            Vec<RoyaltyRecipient>,
+            Option<ContractPackageHash>,
+        )>::cl_type()
+    }
+}
+
+impl ToBytes for Withdrawal {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = Vec::with_capacity(self.serialized_length());
+        result.append(&mut self.amount.to_bytes()?);
+        result.append(&mut self.currency.to_bytes()?);
+        result.append(&mut self.target.to_bytes()?);
+        result.append(&mut self.block_time.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.amount.serialized_length()
+            + self.currency.serialized_length()
+            + self.target.serialized_length()
+            + self.block_time.serialized_length()
+    }
+}
+
+impl FromBytes for Withdrawal {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (amount, bytes) = U512::from_bytes(bytes)?;
+        let (currency, bytes) = Option::<ContractPackageHash>::from_bytes(bytes)?;
+        let (target, bytes) = Key::from_bytes(bytes)?;
+        let (block_time, bytes) = u64::from_bytes(bytes)?;
+        Ok((
+            Self {
+                amount,
+                currency,
+                target,
+                block_time,
+            },
+            bytes,
+        ))
+    }
+}
+
+// Like payment states, withdrawals are laid out like nested tuples of their fields.
+impl CLTyped for Withdrawal {
+    fn cl_type() -> casper_types::CLType {
+        <((U512, Option<ContractPackageHash>), (Key, u64))>::cl_type()
+    }
+}
+
+// A payment state is laid out like an `Option` of its fields, with `Unpaid` being `None`. Tuples
+// have at most three elements, so the fields are grouped into nested tuples, which serialize to the
+// same bytes as the flat fields.
 impl CLTyped for RoyaltyPaymentState {
+    fn cl_type() -> casper_types::CLType {
+        Option::<(
+            (Key, Key, Key),
+            (Key, U512, U512),
+            (Option<ContractPackageHash>, u64, bool),
+        )>::cl_type()
+    }
+}
+
+impl ToBytes for RoyaltyStats {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = Vec::with_capacity(self.serialized_length());
+        result.append(&mut self.payment_count.to_bytes()?);
+        result.append(&mut self.sale_volume.to_bytes()?);
+        result.append(&mut self.royalty_amount.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.payment_count.serialized_length()
+            + self.sale_volume.serialized_length()
+            + self.royalty_amount.serialized_length()
+    }
+}
+
+impl FromBytes for RoyaltyStats {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (payment_count, bytes) = u64::from_bytes(bytes)?;
+        let (sale_volume, bytes) = U512::from_bytes(bytes)?;
+        let (royalty_amount, bytes) = U512::from_bytes(bytes)?;
+        Ok((
+            Self {
+                payment_count,
+                sale_volume,
+                royalty_amount,
+            },
+            bytes,
+        ))
+    }
+}
+
+impl CLTyped for RoyaltyStats {
+    fn cl_type() -> casper_types::CLType {
+        <(u64, U512, U512)>::cl_type()
+    }
+}
+
+impl ToBytes for RoyaltyQuote {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = Vec::with_capacity(self.serialized_length());
+        result.append(&mut self.payment_amount.to_bytes()?);
+        result.append(&mut self.steps.to_bytes()?);
+        result.append(&mut self.total_royalty.to_bytes()?);
+        result.append(&mut self.splits.to_bytes()?);
+        result.append(&mut self.effective_rate.to_bytes()?);
+        result.append(&mut self.currency.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.payment_amount.serialized_length()
+            + self.steps.serialized_length()
+            + self.total_royalty.serialized_length()
+            + self.splits.serialized_length()
+            + self.effective_rate.serialized_length()
+            + self.currency.serialized_length()
+    }
+}
+
+impl FromBytes for RoyaltyQuote {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (payment_amount, bytes) = U512::from_bytes(bytes)?;
+        let (steps, bytes) = Vec::<U512>::from_bytes(bytes)?;
+        let (total_royalty, bytes) = U512::from_bytes(bytes)?;
+        let (splits, bytes) = Vec::<(Key, U512)>::from_bytes(bytes)?;
+        let (effective_rate, bytes) = U256::from_bytes(bytes)?;
+        let (currency, bytes) = Option::<ContractPackageHash>::from_bytes(bytes)?;
+        Ok((
+            Self {
+                payment_amount,
+                steps,
+                total_royalty,
+                splits,
+                effective_rate,
+                currency,
+            },
+            bytes,
+        ))
+    }
+}
+
+// Like payment states, quotes are laid out like nested tuples of their fields.
+impl CLTyped for RoyaltyQuote {
+    fn cl_type() -> casper_types::CLType {
+        <(
+            (U512, Vec<U512>, U512),
+            (Vec<(Key, U512)>, U256, Option<ContractPackageHash>),
+        )>::cl_type()
+    }
+}
+
+impl ToBytes for SettledPayment {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = Vec::with_capacity(self.serialized_length());
+        result.append(&mut self.payer.to_bytes()?);
+        result.append(&mut self.source_key.to_bytes()?);
+        result.append(&mut self.target_key.to_bytes()?);
+        result.append(&mut self.marketplace.to_bytes()?);
+        result.append(&mut self.sale_price.to_bytes()?);
+        result.append(&mut self.amount.to_bytes()?);
+        result.append(&mut self.currency.to_bytes()?);
+        result.append(&mut self.block_time.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.payer.serialized_length()
+            + self.source_key.serialized_length()
+            + self.target_key.serialized_length()
+            + self.marketplace.serialized_length()
+            + self.sale_price.serialized_length()
+            + self.amount.serialized_length()
+            + self.currency.serialized_length()
+            + self.block_time.serialized_length()
+    }
+}
+
+impl FromBytes for SettledPayment {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (payer, bytes) = Key::from_bytes(bytes)?;
+        let (source_key, bytes) = Key::from_bytes(bytes)?;
+        let (target_key, bytes) = Key::from_bytes(bytes)?;
+        let (marketplace, bytes) = Key::from_bytes(bytes)?;
+        let (sale_price, bytes) = U512::from_bytes(bytes)?;
+        let (amount, bytes) = U512::from_bytes(bytes)?;
+        let (currency, bytes) = Option::<ContractPackageHash>::from_bytes(bytes)?;
+        let (block_time, bytes) = u64::from_bytes(bytes)?;
+        Ok((
+            Self {
+                payer,
+                source_key,
+                target_key,
+                marketplace,
+                sale_price,
+                amount,
+                currency,
+                block_time,
+            },
+            bytes,
+        ))
+    }
+}
+
+// A settled payment has the fields of a paid payment state save for `sale`, since only sales are
+// settled into the ledger, and is laid out the same way.
+impl CLTyped for SettledPayment {
+    fn cl_type() -> casper_types::CLType {
+        <(
+            (Key, Key, Key),
+            (Key, U512, U512),
+            (Option<ContractPackageHash>, u64),
+        )>::cl_type()
+    }
+}
+
+impl CLTyped for LegacyRoyaltyPaymentState {
+    fn cl_type() -> casper_types::CLType {
+        casper_types::CLType::Any
+    }
+}
+
+impl CLTyped for LegacyRoyaltyStructure {
     fn cl_type() -> casper_types::CLType {
         casper_types::CLType::Any
     }
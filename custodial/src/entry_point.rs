@@ -3,25 +3,50 @@
 //! Some methods have arguments that are not listed in the returned `EntryPoints` object.
 //! These are either optional or only contextually available. See the documentation of the
 //! individual methods for more information.
+//!
+//! Optional arguments, of type `Option<T>`, are declared with their inner type `T`. Pass a `T`, or
+//! omit the argument for `None`.
 
 use common::{prelude::*, token::TokenIdentifier};
 
-use crate::state::RoyaltyStructure;
+use crate::state::{
+    Currency, RoyaltyPaymentState, RoyaltyQuote, RoyaltyRecipient, RoyaltyStats, RoyaltyStructure,
+    SettledPayment, Withdrawal,
+};
 
 entrypoint! {
+    /// Installs a new custodial contract with the given whitelist, royalty structure and manager,
+    /// which must be an account, along with its transfer filter.
+    /// Pass `contract_package` and the hash of its current version as `previous_contract` instead
+    /// to upgrade an installed package to this version.
     [install] fn call(
-        whitelisted_marketplaces: Vec<ContractPackageHash>,
-        royalty_structure: RoyaltyStructure,
-        manager: Key,
-    ) -> () = crate::install
+        contract_package: Option<ContractPackageHash>,
+        previous_contract: Option<ContractHash>,
+        whitelisted_marketplaces: Option<Vec<ContractPackageHash>>,
+        royalty_structure: Option<RoyaltyStructure>,
+        manager: Option<Key>,
+    ) -> () = crate::call
 }
 
 entrypoints! {
-    [public contract] fn can_transfer(
+    /// Migrates the state of the contract to this version. Only called by the upgrade, which
+    /// passes the transfer filter of the package, installing one if it has none yet.
+    [group(crate::UPGRADE_GROUP) contract] fn migrate(
+        transfer_filter: ContractHash,
+    ) -> () = crate::migrate;
+
+    /// The `can_transfer` of CEP-78, forwarded by the transfer filter along with the collection it
+    /// was called for.
+    [public contract] fn authorize_transfer(
+        token_contract: ContractPackageHash,
         token_id: TokenIdentifier,
         source_key: Key,
-        target_key: Key
-    ) -> u8 = crate::can_transfer;
+        target_key: Key,
+    ) -> u8 = crate::authorize_transfer;
+
+    /// The contract that CEP-78 collections must use as their transfer filter. It is only missing
+    /// while a contract installed without one is being upgraded.
+    [public contract] fn get_transfer_filter() -> Option<ContractHash> = crate::get_transfer_filter;
 
     [public contract] fn pay_royalty(
         token_contract: ContractPackageHash,
@@ -33,9 +58,293 @@ entrypoints! {
         payment_amount: U512,
     ) -> () = crate::pay_royalty;
 
+    [public contract] fn pay_royalty_cep18(
+        token_contract: ContractPackageHash,
+        token_id: TokenIdentifier,
+        payer: Key,
+        source_key: Key,
+        target_key: Key,
+        payment_amount: U512,
+    ) -> () = crate::pay_royalty_cep18;
+
+    /// Paid by the owner of the token from a session, to then transfer it to `target_key` without a
+    /// marketplace. The source purse is only needed for royalties paid in CSPR.
+    [public contract] fn pay_royalty_direct(
+        token_contract: ContractPackageHash,
+        token_id: TokenIdentifier,
+        source_purse: Option<URef>,
+        target_key: Key,
+        payment_amount: U512,
+    ) -> () = crate::pay_royalty_direct;
+
+    /// Paid by the owner of the token from a session, in place of a royalty, to gift it to
+    /// `target_key`. The source purse is only needed if the gift fee is not zero.
+    [public contract] fn pay_gift_fee(
+        token_contract: ContractPackageHash,
+        token_id: TokenIdentifier,
+        source_purse: Option<URef>,
+        target_key: Key,
+    ) -> () = crate::pay_gift_fee;
+
+    /// Omit the gift fee to disable gifts.
+    [public contract] fn set_gift_fee(
+        gift_fee: Option<U512>,
+    ) -> () = crate::set_gift_fee;
+
+    [public contract] fn get_gift_fee() -> Option<U512> = crate::get_gift_fee;
+
+    [public contract] fn set_transfer_exemption(
+        source_key: Key,
+        target_key: Key,
+        exempt: bool,
+    ) -> () = crate::set_transfer_exemption;
+
+    /// Transfers between accounts of the same group need no royalty. Omit the group to remove the
+    /// account from its group.
+    [public contract] fn set_account_group(
+        account: Key,
+        group: Option<u64>,
+    ) -> () = crate::set_account_group;
+
+    [public contract] fn get_account_group(
+        account: Key,
+    ) -> Option<u64> = crate::get_account_group;
+
+    [public contract] fn is_transfer_exempt(
+        source_key: Key,
+        target_key: Key,
+    ) -> bool = crate::is_transfer_exempt;
+
+    /// Pass the `source_key` of the sale, since sales by registered creators are primary sales,
+    /// which may be priced differently. The same applies to `quote_royalty` and
+    /// `get_royalty_currency`.
     [public contract] fn calculate_royalty(
         token_contract: ContractPackageHash,
         token_id: TokenIdentifier,
         payment_amount: U512,
+        source_key: Option<Key>,
     ) -> U512 = crate::calculate_royalty;
-}
\ No newline at end of file
+
+    [public contract] fn quote_royalty(
+        token_contract: ContractPackageHash,
+        token_id: TokenIdentifier,
+        payment_amount: U512,
+        source_key: Option<Key>,
+    ) -> RoyaltyQuote = crate::quote_royalty;
+
+    [public contract] fn get_royalty_currency(
+        token_contract: ContractPackageHash,
+        token_id: TokenIdentifier,
+        source_key: Option<Key>,
+    ) -> Currency = crate::get_royalty_currency;
+
+    /// The payment state is `None` when unpaid, or the tuple `((payer, source_key, target_key),
+    /// (marketplace, sale_price, amount), (currency, paid_at, sale))`, where `sale` tells whether
+    /// the payment is for a sale rather than a gift, and so included in the royalty statistics.
+    [public contract] fn get_payment_state(
+        token_contract: ContractPackageHash,
+        token_id: TokenIdentifier,
+    ) -> RoyaltyPaymentState = crate::get_payment_state;
+
+    [public contract] fn is_transfer_authorized(
+        token_contract: ContractPackageHash,
+        token_id: TokenIdentifier,
+        source_key: Key,
+        target_key: Key,
+    ) -> bool = crate::is_transfer_authorized;
+
+    [public contract] fn get_settled_payment_count(
+        token_contract: ContractPackageHash,
+        token_id: TokenIdentifier,
+    ) -> u64 = crate::get_settled_payment_count;
+
+    [public contract] fn get_settled_payments(
+        token_contract: ContractPackageHash,
+        token_id: TokenIdentifier,
+        offset: u64,
+        limit: u64,
+    ) -> Vec<SettledPayment> = crate::get_settled_payments;
+
+    /// Totals of the royalties paid for a collection in `currency`, net of refunds.
+    [public contract] fn get_collection_stats(
+        token_contract: ContractPackageHash,
+        currency: Currency,
+    ) -> RoyaltyStats = crate::get_collection_stats;
+
+    /// Totals of the royalties paid through a marketplace in `currency`, net of refunds. Pass the
+    /// package of a marketplace contract as a `Key::Hash`, or the account of an owner that paid
+    /// directly or for a gift.
+    [public contract] fn get_marketplace_stats(
+        marketplace: Key,
+        currency: Currency,
+    ) -> RoyaltyStats = crate::get_marketplace_stats;
+
+    [public contract] fn refund_royalty(
+        token_contract: ContractPackageHash,
+        token_id: TokenIdentifier,
+        target: Key,
+    ) -> () = crate::refund_royalty;
+
+    [public contract] fn get_manager() -> Key = crate::get_manager;
+
+    /// The manager may be an account, or a contract package given as a hash key.
+    [public contract] fn propose_manager(
+        manager: Key,
+    ) -> () = crate::propose_manager;
+
+    [public contract] fn accept_manager() -> () = crate::accept_manager;
+
+    [public contract] fn get_pending_manager() -> Option<Key> = crate::get_pending_manager;
+
+    /// While paused, royalties cannot be paid and the transfer filter denies every transfer. With
+    /// `allow_manager_recovery`, transfers initiated by the manager itself are still allowed.
+    [public contract] fn set_paused(
+        paused: bool,
+        allow_manager_recovery: bool,
+    ) -> () = crate::set_paused;
+
+    [public contract] fn is_paused() -> bool = crate::is_paused;
+
+    /// Unsettled payments expire after `expiry` milliseconds of block time, a day by default. After
+    /// that, they no longer authorize a transfer, and can be refunded or replaced.
+    [public contract] fn set_payment_expiry(
+        expiry: u64,
+    ) -> () = crate::set_payment_expiry;
+
+    [public contract] fn set_royalty_structure(
+        royalty_structure: RoyaltyStructure,
+    ) -> () = crate::set_royalty_structure;
+
+    [public contract] fn get_royalty_structure() -> RoyaltyStructure = crate::get_royalty_structure;
+
+    [public contract] fn set_royalty_recipients(
+        recipients: Vec<RoyaltyRecipient>,
+    ) -> () = crate::set_royalty_recipients;
+
+    /// Omit the token identifier to override the royalty structure of a whole collection.
+    [public contract] fn set_royalty_override(
+        token_contract: ContractPackageHash,
+        token_id: Option<TokenIdentifier>,
+        royalty_structure: RoyaltyStructure,
+    ) -> () = crate::set_royalty_override;
+
+    [public contract] fn remove_royalty_override(
+        token_contract: ContractPackageHash,
+        token_id: Option<TokenIdentifier>,
+    ) -> () = crate::remove_royalty_override;
+
+    [public contract] fn get_royalty_override(
+        token_contract: ContractPackageHash,
+        token_id: Option<TokenIdentifier>,
+    ) -> Option<RoyaltyStructure> = crate::get_royalty_override;
+
+    [public contract] fn add_creator(
+        creator: Key,
+    ) -> () = crate::add_creator;
+
+    [public contract] fn remove_creator(
+        creator: Key,
+    ) -> () = crate::remove_creator;
+
+    [public contract] fn is_creator(
+        creator: Key,
+    ) -> bool = crate::is_creator;
+
+    /// Primary sales, made by a registered creator or, unless disabled with
+    /// `set_first_sale_primary`, being the first recorded sale of a token, use this structure
+    /// instead of the regular one. It may have no steps to exempt them.
+    [public contract] fn set_primary_sale_structure(
+        royalty_structure: RoyaltyStructure,
+    ) -> () = crate::set_primary_sale_structure;
+
+    [public contract] fn remove_primary_sale_structure(
+    ) -> () = crate::remove_primary_sale_structure;
+
+    [public contract] fn get_primary_sale_structure(
+    ) -> Option<RoyaltyStructure> = crate::get_primary_sale_structure;
+
+    /// Whether the first recorded sale of a token is a primary sale. Enabled on install, and
+    /// disabled for contracts upgraded from a version without a ledger of sales, whose tokens may
+    /// have been sold before.
+    [public contract] fn set_first_sale_primary(
+        first_sale_primary: bool,
+    ) -> () = crate::set_first_sale_primary;
+
+    [public contract] fn is_first_sale_primary() -> bool = crate::is_first_sale_primary;
+
+    [public contract] fn add_whitelisted_marketplace(
+        marketplace: ContractPackageHash,
+    ) -> () = crate::add_whitelisted_marketplace;
+
+    [public contract] fn remove_whitelisted_marketplace(
+        marketplace: ContractPackageHash,
+    ) -> () = crate::remove_whitelisted_marketplace;
+
+    [public contract] fn set_account_whitelisted(
+        account: Key,
+        whitelisted: bool,
+    ) -> () = crate::set_account_whitelisted;
+
+    [public contract] fn is_account_whitelisted(
+        account: Key,
+    ) -> bool = crate::is_account_whitelisted;
+
+    [public contract] fn set_whitelist_enabled(
+        enabled: bool,
+    ) -> () = crate::set_whitelist_enabled;
+
+    /// Whitelisted marketplaces only restrict payments while the whitelist is enabled.
+    [public contract] fn is_marketplace_whitelisted(
+        marketplace: ContractPackageHash,
+    ) -> bool = crate::is_marketplace_whitelisted;
+
+    [public contract] fn is_whitelist_enabled() -> bool = crate::is_whitelist_enabled;
+
+    [public contract] fn get_whitelisted_marketplaces(
+        offset: u64,
+        limit: u64,
+    ) -> Vec<ContractPackageHash> = crate::get_whitelisted_marketplaces;
+
+    [public contract] fn withdraw_royalties(
+        amount: U512,
+        target: Key,
+        currency: Currency,
+    ) -> () = crate::withdraw_royalties;
+
+    [public contract] fn royalty_balance(
+        currency: Currency,
+    ) -> U512 = crate::royalty_balance;
+
+    [public contract] fn get_withdrawals(
+        offset: u64,
+        limit: u64,
+    ) -> Vec<Withdrawal> = crate::get_withdrawals;
+
+    [public contract] fn claim(
+        target: Key,
+        currency: Currency,
+    ) -> () = crate::claim;
+
+    [public contract] fn claimable_balance(
+        recipient: Key,
+        currency: Currency,
+    ) -> U512 = crate::claimable_balance;
+
+    [public contract] fn set_currency_accepted(
+        currency: ContractPackageHash,
+        accepted: bool,
+    ) -> () = crate::set_currency_accepted;
+}
+
+/// Entry points of the transfer filter, see [`crate::filter`].
+pub mod filter {
+    use super::*;
+
+    entrypoints! {
+        [public contract] fn can_transfer(
+            token_id: TokenIdentifier,
+            source_key: Key,
+            target_key: Key,
+        ) -> u8 = crate::filter::can_transfer;
+    }
+}
@@ -0,0 +1,174 @@
+//! Events emitted by the contract, following the Casper Event Standard.
+//!
+//! Events about a single token identify it the way CEP-78 does, by `token_id` for ordinal
+//! identifiers or by `token_hash` for hash identifiers, see [`token_identifier`].
+//!
+//! Denied transfers emit no event: CEP-78 reverts a transfer that its filter denies, and every
+//! event emitted during it along with it. Use `is_transfer_authorized` to check a transfer instead.
+use common::{events::Schemas, prelude::*, token::TokenIdentifier};
+
+use crate::state::{Currency, RoyaltyStructure};
+
+serializable_events! {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RoyaltyPaid {
+        pub token_contract: ContractPackageHash,
+        pub token_id: Option<u64>,
+        pub token_hash: Option<String>,
+        pub payer: Key,
+        pub source_key: Key,
+        pub target_key: Key,
+        pub marketplace: Key,
+        pub sale_price: U512,
+        pub amount: U512,
+        pub currency: Currency,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RoyaltyRefunded {
+        pub token_contract: ContractPackageHash,
+        pub token_id: Option<u64>,
+        pub token_hash: Option<String>,
+        pub target: Key,
+        pub amount: U512,
+        pub currency: Currency,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct TransferAuthorized {
+        pub token_contract: ContractPackageHash,
+        pub token_id: Option<u64>,
+        pub token_hash: Option<String>,
+        pub source_key: Key,
+        pub target_key: Key,
+    }
+
+    /// Emitted when the default royalty structure changes, or when an override is set or removed.
+    /// Overrides of a whole collection have neither `token_id` nor `token_hash`, and removed
+    /// overrides have no `royalty_structure`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RoyaltyStructureChanged {
+        pub token_contract: Option<ContractPackageHash>,
+        pub token_id: Option<u64>,
+        pub token_hash: Option<String>,
+        pub royalty_structure: Option<RoyaltyStructure>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct PrimarySaleStructureChanged {
+        pub royalty_structure: Option<RoyaltyStructure>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct CreatorAdded {
+        pub creator: Key,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct CreatorRemoved {
+        pub creator: Key,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct GiftFeeChanged {
+        pub gift_fee: Option<U512>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct TransferExemptionChanged {
+        pub source_key: Key,
+        pub target_key: Key,
+        pub exempt: bool,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct AccountGroupChanged {
+        pub account: Key,
+        pub group: Option<u64>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RoyaltyClaimed {
+        pub recipient: Key,
+        pub target: Key,
+        pub amount: U512,
+        pub currency: Currency,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RoyaltiesWithdrawn {
+        pub target: Key,
+        pub amount: U512,
+        pub currency: Currency,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct PauseChanged {
+        pub paused: bool,
+        pub allow_manager_recovery: bool,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ManagerProposed {
+        pub manager: Key,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ManagerChanged {
+        pub previous_manager: Key,
+        pub manager: Key,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct MarketplaceWhitelisted {
+        pub marketplace: ContractPackageHash,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct MarketplaceRemovedFromWhitelist {
+        pub marketplace: ContractPackageHash,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct AccountWhitelistChanged {
+        pub account: Key,
+        pub whitelisted: bool,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct WhitelistEnabledChanged {
+        pub enabled: bool,
+    }
+}
+
+/// Split a token identifier into the `token_id` and `token_hash` fields of an event, only one of
+/// which is set.
+pub fn token_identifier(token_id: &TokenIdentifier) -> (Option<u64>, Option<String>) {
+    match token_id {
+        TokenIdentifier::Index(index) => (Some(*index), None),
+        TokenIdentifier::Hash(hash) => (None, Some(hash.clone())),
+    }
+}
+
+pub fn schemas() -> Schemas {
+    Schemas::new()
+        .with::<RoyaltyPaid>()
+        .with::<RoyaltyRefunded>()
+        .with::<TransferAuthorized>()
+        .with::<RoyaltyStructureChanged>()
+        .with::<PrimarySaleStructureChanged>()
+        .with::<CreatorAdded>()
+        .with::<CreatorRemoved>()
+        .with::<GiftFeeChanged>()
+        .with::<TransferExemptionChanged>()
+        .with::<AccountGroupChanged>()
+        .with::<RoyaltyClaimed>()
+        .with::<RoyaltiesWithdrawn>()
+        .with::<PauseChanged>()
+        .with::<ManagerProposed>()
+        .with::<ManagerChanged>()
+        .with::<MarketplaceWhitelisted>()
+        .with::<MarketplaceRemovedFromWhitelist>()
+        .with::<AccountWhitelistChanged>()
+        .with::<WhitelistEnabledChanged>()
+}
@@ -0,0 +1,54 @@
+//! The transfer filter of the CEP-78 collections governed by a custodial contract.
+//!
+//! CEP-78 calls its transfer filter by contract hash, which changes with every version of the
+//! custodial package. Collections are therefore pointed to this small contract instead, which is
+//! locked so that its hash never changes, and forwards every call to the latest version of the
+//! custodial package.
+use alloc::{collections::BTreeMap, string::ToString};
+use common::{call_stack::CallStackElementEx, o_unwrap, prelude::*, token::TokenIdentifier};
+
+use crate::{entry_point, CustodialError};
+
+/// Named key of the filter contract under which the custodial package is stored.
+pub const NK_CUSTODIAL_PACKAGE: &str = "custodial_package";
+
+/// Install a new filter for `custodial_package`, returning the hash of its contract.
+pub fn install(custodial_package: ContractPackageHash) -> ContractHash {
+    let mut named_keys = BTreeMap::new();
+    named_keys.insert(
+        NK_CUSTODIAL_PACKAGE.to_string(),
+        Key::Hash(custodial_package.value()),
+    );
+
+    let entry_points = entry_point::filter::all_entrypoints().into();
+    let (contract_hash, _) =
+        storage::new_locked_contract(entry_points, Some(named_keys), None, None);
+
+    contract_hash
+}
+
+// The custodial contract cannot tell which collection a forwarded call is for, so the filter passes
+// its own caller along.
+pub(crate) fn can_transfer(token_id: TokenIdentifier, source_key: Key, target_key: Key) -> u8 {
+    let token_contract = o_unwrap!(
+        common::call_stack::caller().contract_package(),
+        CustodialError::CallerMustBeContract
+    );
+    let custodial_package = o_unwrap!(
+        runtime::get_key(NK_CUSTODIAL_PACKAGE).and_then(|key| key.into_hash()),
+        ApiError::MissingKey
+    );
+
+    runtime::call_versioned_contract(
+        ContractPackageHash::new(custodial_package),
+        None,
+        "authorize_transfer",
+        vec![
+            named_arg!(token_contract),
+            token_id.to_named_arg(),
+            named_arg!(source_key),
+            named_arg!(target_key),
+        ]
+        .into(),
+    )
+}
@@ -1,22 +1,35 @@
 #![no_std]
 
-use alloc::{collections::BTreeMap, string::ToString};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::ToString,
+};
 use common::{
-    call_stack::CallStackElementEx, o_unwrap, prelude::*, store_named_key_incremented, token::TokenIdentifier, ToStrKey
+    call_stack::CallStackElementEx, o_unwrap, prelude::*, r_unwrap, store_named_key_incremented, token::TokenIdentifier
+};
+use state::{
+    Currency, RoyaltyPaymentState, RoyaltyQuote, RoyaltyRecipient, RoyaltyStats, RoyaltyStructure,
+    SaleContext, SettledPayment, Withdrawal,
 };
-use state::{RoyaltyPaymentState, RoyaltyStructure};
 
 extern crate alloc;
 
 mod bytes;
 pub mod entry_point;
+mod events;
+pub mod filter;
 pub mod state;
 
 pub const NK_ACCESS_UREF: &str = "cep82_custodial_uref";
 pub const NK_CONTRACT: &str = "cep82_custodial";
+pub const NK_TRANSFER_FILTER: &str = "cep82_transfer_filter";
 pub const NK_ROYALTY_PURSE: &str = "royalty_purse";
 pub const NAME: &str = "custodial";
 
+/// User group of the package that may call `migrate`. It only has a member for the duration of an
+/// upgrade.
+pub const UPGRADE_GROUP: &str = "upgrade";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u16)]
 pub enum CustodialError {
@@ -29,6 +42,37 @@ pub enum CustodialError {
     MustPayRoyalties = 106,
     Overflow = 107,
     InvalidRoyaltyScheme = 108,
+    CallerMustBeManager = 109,
+    MarketplaceAlreadyWhitelisted = 110,
+    InsufficientBalance = 111,
+    InvalidTransferTarget = 112,
+    TransferFailed = 113,
+    NothingToClaim = 114,
+    PaymentNotFound = 115,
+    CallerMustBePayer = 116,
+    PaymentNotExpired = 117,
+    PaymentOutstanding = 118,
+    UnsupportedCurrency = 119,
+    InvalidManager = 120,
+    CallerMustBePendingManager = 121,
+    ContractPaused = 122,
+    PercentageOutOfRange = 123,
+    DuplicateMinimumStep = 124,
+    InvalidBracket = 125,
+    TotalPercentageOutOfRange = 126,
+    FlatAmountsOverflow = 127,
+    InvalidRecipientShare = 128,
+    DuplicateRecipient = 129,
+    InvalidRoyaltyWindow = 130,
+    CreatorAlreadyRegistered = 131,
+    CreatorNotRegistered = 132,
+    GiftsDisabled = 133,
+    CallerMustBeAccount = 134,
+    AccountNotWhitelisted = 135,
+    InvalidRecipient = 136,
+    InvalidSalePrice = 137,
+    InvalidPaymentExpiry = 138,
+    CallerMustBeTransferFilter = 139,
 }
 
 impl From<CustodialError> for ApiError {
@@ -37,23 +81,66 @@ impl From<CustodialError> for ApiError {
     }
 }
 
+// Installs a new custodial contract, unless `contract_package` is given, in which case a new
+// version of that package replaces `previous_contract` instead.
+fn call(
+    contract_package: Option<ContractPackageHash>,
+    previous_contract: Option<ContractHash>,
+    whitelisted_marketplaces: Option<Vec<ContractPackageHash>>,
+    royalty_structure: Option<RoyaltyStructure>,
+    manager: Option<Key>,
+) {
+    match contract_package {
+        Some(contract_package) => upgrade(
+            contract_package,
+            o_unwrap!(previous_contract, ApiError::MissingArgument),
+        ),
+        None => install(
+            o_unwrap!(whitelisted_marketplaces, ApiError::MissingArgument),
+            o_unwrap!(royalty_structure, ApiError::MissingArgument),
+            o_unwrap!(manager, ApiError::MissingArgument),
+        ),
+    }
+}
+
 fn install(
     whitelisted_marketplaces: Vec<ContractPackageHash>,
     royalty_structure: RoyaltyStructure,
     manager: Key,
 ) {
+    // A contract manager must prove that it can call the contract by accepting the handover, so
+    // installs only take an account.
+    ensure!(
+        matches!(manager, Key::Account(_)),
+        CustodialError::InvalidManager
+    );
+
+    let (contract_package_hash, access_uref) = storage::create_contract_package_at_hash();
+    // The group starts out empty, so that nobody can call `migrate` outside of an upgrade.
+    storage::create_contract_user_group(contract_package_hash, UPGRADE_GROUP, 0, BTreeSet::new())
+        .unwrap_or_revert();
+    let transfer_filter = install_transfer_filter(contract_package_hash);
+
     let royalty_purse = casper_contract::contract_api::system::create_purse();
-    let mut named_keys: BTreeMap<_, _> = state::init_all(manager, royalty_structure)
-        .into_iter()
-        .collect::<_>();
+    let mut named_keys: BTreeMap<_, _> =
+        state::init_all(manager, royalty_structure.clone(), transfer_filter)
+            .into_iter()
+            .collect::<_>();
+
+    // The currency of the default royalty structure is accepted from the start. This needs the
+    // named keys created above, and so does validating the structure.
+    if let Some(currency) = royalty_structure.currency {
+        state::set_currency_accepted(currency, true);
+    }
+    royalty_structure.validate();
 
     named_keys.insert(NK_ROYALTY_PURSE.to_string(), royalty_purse.into());
+    named_keys.extend(common::events::init(events::schemas()));
 
     let entry_points = entry_point::all_entrypoints().into();
 
     init(whitelisted_marketplaces);
 
-    let (contract_package_hash, access_uref) = storage::create_contract_package_at_hash();
     let (contract_hash, _) =
         storage::add_contract_version(contract_package_hash, entry_points, named_keys);
 
@@ -61,6 +148,74 @@ fn install(
     store_named_key_incremented(contract_hash.into(), NK_CONTRACT);
 }
 
+// The new version inherits the named keys of the previous one, including the royalty purse, so
+// only the entry points are replaced. The state is then migrated from within the new version.
+// Adding a version requires the access URef of the package, which the installing account holds.
+//
+// The previous version is disabled, since it would bypass the checks of the new one and write the
+// migrated state in its own layout. Collections keep calling the same transfer filter, which
+// forwards to the latest version; contracts installed before the filter existed get one here.
+//
+// `migrate` is restricted to the upgrade group, which this session joins for the duration of the
+// upgrade only.
+fn upgrade(contract_package: ContractPackageHash, previous_contract: ContractHash) {
+    let upgrade_uref = match storage::create_contract_user_group(
+        contract_package,
+        UPGRADE_GROUP,
+        1,
+        BTreeSet::new(),
+    ) {
+        Ok(urefs) => urefs[0],
+        // Packages installed with the group already have it.
+        Err(_) => storage::provision_contract_user_group_uref(contract_package, UPGRADE_GROUP)
+            .unwrap_or_revert(),
+    };
+
+    let entry_points = entry_point::all_entrypoints().into();
+    let (contract_hash, _) =
+        storage::add_contract_version(contract_package, entry_points, BTreeMap::new());
+    storage::disable_contract_version(contract_package, previous_contract).unwrap_or_revert();
+
+    let transfer_filter = runtime::call_contract::<Option<ContractHash>>(
+        contract_hash,
+        "get_transfer_filter",
+        casper_types::RuntimeArgs::new(),
+    )
+    .unwrap_or_else(|| install_transfer_filter(contract_package));
+
+    runtime::call_contract::<()>(
+        contract_hash,
+        "migrate",
+        vec![named_arg!(transfer_filter)].into(),
+    );
+
+    storage::remove_contract_user_group_urefs(
+        contract_package,
+        UPGRADE_GROUP,
+        BTreeSet::from([upgrade_uref]),
+    )
+    .unwrap_or_revert();
+
+    store_named_key_incremented(contract_hash.into(), NK_CONTRACT);
+}
+
+// The filter is installed by the installing account, which keeps its hash to point collections to.
+fn install_transfer_filter(contract_package: ContractPackageHash) -> ContractHash {
+    let transfer_filter = filter::install(contract_package);
+    store_named_key_incremented(transfer_filter.into(), NK_TRANSFER_FILTER);
+
+    transfer_filter
+}
+
+fn migrate(transfer_filter: ContractHash) {
+    common::events::upgrade(events::schemas());
+    state::migrate(transfer_filter);
+}
+
+fn get_transfer_filter() -> Option<ContractHash> {
+    runtime::get_key(state::transfer_filter::NAME).map(|_| state::transfer_filter::read())
+}
+
 fn init(whitelisted_marketplaces: Vec<ContractPackageHash>) {
     if whitelisted_marketplaces.is_empty() {
         state::marketplace_whitelist_enabled::write(false);
@@ -68,8 +223,7 @@ fn init(whitelisted_marketplaces: Vec<ContractPackageHash>) {
         state::marketplace_whitelist_enabled::write(true);
 
         for marketplace in whitelisted_marketplaces {
-            let marketplace_key = marketplace.to_key();
-            state::whitelisted_marketplaces::write(&marketplace_key, true);
+            state::add_whitelisted_marketplace(marketplace);
         }
     }
 }
@@ -83,6 +237,182 @@ fn pay_royalty(
     target_key: Key,
     payment_amount: U512,
 ) {
+    // Royalties never exceed the sale price, so a sale price of zero would waive any royalty.
+    ensure!(!payment_amount.is_zero(), CustodialError::InvalidSalePrice);
+
+    let royalty_structure =
+        authorize_royalty_payment(token_contract, &token_id, source_key, target_key);
+    ensure!(
+        royalty_structure.currency.is_none(),
+        CustodialError::UnsupportedCurrency
+    );
+
+    let sale = sale_context(token_contract, &token_id, &royalty_structure);
+    let total_royalty = royalty_structure.calculate_total_royalty(payment_amount, &sale);
+
+    contract_api::system::transfer_from_purse_to_purse(
+        source_purse,
+        royalty_purse(),
+        total_royalty,
+        None,
+    )
+    .unwrap_or_revert();
+
+    record_royalty_payment(
+        token_contract,
+        &token_id,
+        payer,
+        source_key,
+        target_key,
+        payment_amount,
+        total_royalty,
+        None,
+    );
+}
+
+// The marketplace pays the royalty out of its own CEP-18 balance, which it must have approved the
+// custodial contract to spend beforehand.
+fn pay_royalty_cep18(
+    token_contract: ContractPackageHash,
+    token_id: TokenIdentifier,
+    payer: Key,
+    source_key: Key,
+    target_key: Key,
+    payment_amount: U512,
+) {
+    ensure!(!payment_amount.is_zero(), CustodialError::InvalidSalePrice);
+
+    let royalty_structure =
+        authorize_royalty_payment(token_contract, &token_id, source_key, target_key);
+    let currency = o_unwrap!(
+        royalty_structure.currency,
+        CustodialError::UnsupportedCurrency
+    );
+    ensure!(
+        state::is_currency_accepted(currency),
+        CustodialError::UnsupportedCurrency
+    );
+
+    let sale = sale_context(token_contract, &token_id, &royalty_structure);
+    let total_royalty = royalty_structure.calculate_total_royalty(payment_amount, &sale);
+
+    common::ext::erc20::transfer_from(
+        currency,
+        common::call_stack::caller().key(),
+        this_package_key(),
+        to_token_amount(total_royalty),
+    );
+
+    record_royalty_payment(
+        token_contract,
+        &token_id,
+        payer,
+        source_key,
+        target_key,
+        payment_amount,
+        total_royalty,
+        Some(currency),
+    );
+}
+
+// Owners may also sell a token without a marketplace: they pay the royalty on the declared price
+// from a session, and then transfer the token to `target_key` themselves. CSPR royalties are paid
+// from `source_purse`, CEP-18 royalties from the token balance of the owner, which must have
+// approved the custodial contract to spend them.
+fn pay_royalty_direct(
+    token_contract: ContractPackageHash,
+    token_id: TokenIdentifier,
+    source_purse: Option<URef>,
+    target_key: Key,
+    payment_amount: U512,
+) {
+    ensure!(!payment_amount.is_zero(), CustodialError::InvalidSalePrice);
+
+    let source_key = authorize_direct_payment(token_contract, &token_id, target_key);
+
+    let royalty_structure = resolve_royalty_structure(token_contract, &token_id, Some(source_key));
+    let sale = sale_context(token_contract, &token_id, &royalty_structure);
+    let total_royalty = royalty_structure.calculate_total_royalty(payment_amount, &sale);
+
+    match royalty_structure.currency {
+        Some(currency) => {
+            ensure!(
+                state::is_currency_accepted(currency),
+                CustodialError::UnsupportedCurrency
+            );
+            common::ext::erc20::transfer_from(
+                currency,
+                source_key,
+                this_package_key(),
+                to_token_amount(total_royalty),
+            );
+        }
+        None => {
+            contract_api::system::transfer_from_purse_to_purse(
+                o_unwrap!(source_purse, ApiError::MissingArgument),
+                royalty_purse(),
+                total_royalty,
+                None,
+            )
+            .unwrap_or_revert();
+        }
+    }
+
+    record_royalty_payment(
+        token_contract,
+        &token_id,
+        source_key,
+        source_key,
+        target_key,
+        payment_amount,
+        total_royalty,
+        royalty_structure.currency,
+    );
+}
+
+// Checks that the calling account owns the token and may pay for its transfer without a
+// marketplace, and clears the way for the new payment. Returns the calling account.
+fn authorize_direct_payment(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+    target_key: Key,
+) -> Key {
+    ensure!(!state::paused::read(), CustodialError::ContractPaused);
+
+    let source_key = common::call_stack::caller().key();
+    ensure!(
+        matches!(source_key, Key::Account(_)),
+        CustodialError::CallerMustBeAccount
+    );
+    ensure!(
+        !state::marketplace_whitelist_enabled::read() || state::is_account_whitelisted(source_key),
+        CustodialError::AccountNotWhitelisted
+    );
+    ensure_neq!(
+        source_key,
+        target_key,
+        CustodialError::SelfTransferForbidden
+    );
+    ensure_eq!(
+        common::ext::cep78::owner_of(token_contract, token_id),
+        source_key,
+        CustodialError::SourceMustBeOwner
+    );
+
+    replace_payment(token_contract, token_id, source_key);
+
+    source_key
+}
+
+// Checks that the calling marketplace may pay the royalty of the transfer and clears the way for
+// the new payment, returning the royalty structure that applies to the token.
+fn authorize_royalty_payment(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+    source_key: Key,
+    target_key: Key,
+) -> RoyaltyStructure {
+    ensure!(!state::paused::read(), CustodialError::ContractPaused);
     ensure_neq!(
         source_key,
         target_key,
@@ -107,7 +437,7 @@ fn pay_royalty(
     ensure!(is_whitelisted, CustodialError::MarketplaceNotWhitelisted);
 
     let approved = o_unwrap!(
-        common::ext::cep78::get_approved(token_contract, &token_id),
+        common::ext::cep78::get_approved(token_contract, token_id),
         CustodialError::CallerMustBeApproved
     );
     
@@ -117,81 +447,916 @@ fn pay_royalty(
         CustodialError::CallerMustBeApproved
     );
 
-    let current_owner = common::ext::cep78::owner_of(token_contract, &token_id);
+    let current_owner = common::ext::cep78::owner_of(token_contract, token_id);
     ensure_eq!(current_owner, source_key, CustodialError::SourceMustBeOwner);
 
-    let royalty_purse = runtime::get_key(NK_ROYALTY_PURSE)
-        .unwrap_or_revert()
-        .into_uref()
-        .unwrap_or_revert();
+    replace_payment(token_contract, token_id, source_key);
 
-    let token_key = token_id.to_key();
-    let total_royalty = calculate_royalty(token_contract, token_id, payment_amount);
+    resolve_royalty_structure(token_contract, token_id, Some(source_key))
+}
 
-    let old_payment_state = state::royalty_payments::try_read(&token_key);
-    if let Some(RoyaltyPaymentState::Paid {
+// Clears the way for a new payment from `source_key`. A payment from the same source must expire
+// before it can be replaced.
+fn replace_payment(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+    source_key: Key,
+) {
+    let old_payment_state = state::read_payment_state(token_contract, token_id);
+    if let RoyaltyPaymentState::Paid {
+        payer: paid_payer,
         source_key: paid_source_key,
+        marketplace: paid_marketplace,
+        sale_price: paid_sale_price,
+        amount: paid_amount,
+        currency: paid_currency,
+        paid_at,
+        sale,
         ..
-    }) = old_payment_state
+    } = old_payment_state
     {
-        ensure_neq!(source_key, paid_source_key, CustodialError::AlreadyPaid)
+        if source_key == paid_source_key {
+            ensure!(
+                state::is_payment_expired(paid_at),
+                CustodialError::AlreadyPaid
+            );
+        }
+
+        refund_replaced_payment(paid_payer, paid_amount, paid_currency);
+        if sale {
+            state::remove_royalty_stats(
+                token_contract,
+                paid_marketplace,
+                paid_currency,
+                paid_sale_price,
+                paid_amount,
+            );
+        }
     }
+}
 
-    contract_api::system::transfer_from_purse_to_purse(
-        source_purse,
-        royalty_purse,
-        total_royalty,
+// Owners gifting a token pay the flat gift fee in place of a royalty, from a session of their own
+// account, subject to the same checks as royalties paid directly. Gifts are recorded with a sale
+// price of zero, and are kept out of the royalty statistics and the ledger of sales.
+fn pay_gift_fee(
+    token_contract: ContractPackageHash,
+    token_id: TokenIdentifier,
+    source_purse: Option<URef>,
+    target_key: Key,
+) {
+    let gift_fee = o_unwrap!(state::gift_fee::read(), CustodialError::GiftsDisabled);
+
+    let source_key = authorize_direct_payment(token_contract, &token_id, target_key);
+
+    if !gift_fee.is_zero() {
+        contract_api::system::transfer_from_purse_to_purse(
+            o_unwrap!(source_purse, ApiError::MissingArgument),
+            royalty_purse(),
+            gift_fee,
+            None,
+        )
+        .unwrap_or_revert();
+    }
+
+    record_royalty_payment(
+        token_contract,
+        &token_id,
+        source_key,
+        source_key,
+        target_key,
+        U512::zero(),
+        gift_fee,
         None,
-    )
-    .unwrap_or_revert();
+    );
+}
+
+fn set_gift_fee(gift_fee: Option<U512>) {
+    ensure_caller_is_manager();
+    state::gift_fee::write(gift_fee);
+
+    common::events::emit(&events::GiftFeeChanged { gift_fee });
+}
+
+fn get_gift_fee() -> Option<U512> {
+    state::gift_fee::read()
+}
+
+fn set_transfer_exemption(source_key: Key, target_key: Key, exempt: bool) {
+    ensure_caller_is_manager();
+    state::set_transfer_exemption(source_key, target_key, exempt);
+
+    common::events::emit(&events::TransferExemptionChanged {
+        source_key,
+        target_key,
+        exempt,
+    });
+}
+
+fn set_account_group(account: Key, group: Option<u64>) {
+    ensure_caller_is_manager();
+    state::set_account_group(account, group);
+
+    common::events::emit(&events::AccountGroupChanged { account, group });
+}
+
+fn get_account_group(account: Key) -> Option<u64> {
+    state::account_group(account)
+}
 
+fn is_transfer_exempt(source_key: Key, target_key: Key) -> bool {
+    state::is_transfer_exempt(source_key, target_key)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_royalty_payment(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+    payer: Key,
+    source_key: Key,
+    target_key: Key,
+    sale_price: U512,
+    amount: U512,
+    currency: Currency,
+) {
+    state::reserve_pending_royalties(currency, amount);
+
+    // Sales cannot have a price of zero, so only gifts do.
+    let sale = !sale_price.is_zero();
+    let marketplace = common::call_stack::caller().key();
     let payment_state = RoyaltyPaymentState::Paid {
         payer,
         source_key,
-        amount: total_royalty,
+        target_key,
+        marketplace,
+        sale_price,
+        amount,
+        currency,
+        paid_at: runtime::get_blocktime().into(),
+        sale,
     };
 
-    state::royalty_payments::write(&token_key, payment_state);
+    state::write_payment_state(token_contract, token_id, payment_state);
+    if sale {
+        state::add_royalty_stats(token_contract, marketplace, currency, sale_price, amount);
+    }
+
+    let (token_id, token_hash) = events::token_identifier(token_id);
+    common::events::emit(&events::RoyaltyPaid {
+        token_contract,
+        token_id,
+        token_hash,
+        payer,
+        source_key,
+        target_key,
+        marketplace,
+        sale_price,
+        amount,
+        currency,
+    });
 }
 
-// This sample custodial implementation applies the same royalty regardless
-// of the token. The interface is left open for those who wish to implement
-// a more sophisticated royalty scheme.
-fn calculate_royalty(
-    _token_contract: ContractPackageHash,
-    _token_id: TokenIdentifier,
-    payment_amount: U512,
-) -> U512 {
-    let royalty_structure = o_unwrap!(
+// A payment that gets replaced would never authorize a transfer again, so it is refunded to its
+// payer right away. Payers that cannot receive CSPR directly have to refund it themselves first.
+fn refund_replaced_payment(payer: Key, amount: U512, currency: Currency) {
+    ensure!(
+        currency.is_some() || matches!(payer, Key::Account(_)),
+        CustodialError::PaymentOutstanding
+    );
+
+    state::release_pending_royalties(currency, amount);
+    pay_out(currency, payer, amount);
+}
+
+// Payments can be refunded once they expire, or as soon as their source no longer owns the token,
+// since they cannot authorize any transfer from then on.
+fn refund_royalty(token_contract: ContractPackageHash, token_id: TokenIdentifier, target: Key) {
+    let RoyaltyPaymentState::Paid {
+        payer,
+        source_key,
+        marketplace,
+        sale_price,
+        amount,
+        currency,
+        paid_at,
+        sale,
+        ..
+    } = state::read_payment_state(token_contract, &token_id) else {
+        revert(CustodialError::PaymentNotFound);
+    };
+
+    let caller = common::call_stack::caller().key();
+    ensure!(
+        caller == payer || caller == marketplace,
+        CustodialError::CallerMustBePayer
+    );
+    ensure!(
+        state::is_payment_expired(paid_at)
+            || common::ext::cep78::owner_of(token_contract, &token_id) != source_key,
+        CustodialError::PaymentNotExpired
+    );
+
+    state::write_payment_state(token_contract, &token_id, RoyaltyPaymentState::Unpaid);
+    state::release_pending_royalties(currency, amount);
+    if sale {
+        state::remove_royalty_stats(token_contract, marketplace, currency, sale_price, amount);
+    }
+
+    pay_out(currency, target, amount);
+
+    let (token_id, token_hash) = events::token_identifier(&token_id);
+    common::events::emit(&events::RoyaltyRefunded {
+        token_contract,
+        token_id,
+        token_hash,
+        target,
+        amount,
+        currency,
+    });
+}
+
+// Control is handed over in two steps, so that it cannot be lost to a mistyped key. Proposing a new
+// manager replaces any earlier proposal.
+fn propose_manager(manager: Key) {
+    ensure_caller_is_manager();
+    ensure!(
+        state::is_valid_manager(manager),
+        CustodialError::InvalidManager
+    );
+
+    state::pending_manager::write(Some(manager));
+
+    common::events::emit(&events::ManagerProposed { manager });
+}
+
+fn accept_manager() {
+    let caller = common::call_stack::caller().key();
+    ensure_eq!(
+        Some(caller),
+        state::pending_manager::read(),
+        CustodialError::CallerMustBePendingManager
+    );
+
+    let previous_manager = state::manager::read();
+    state::manager::write(caller);
+    state::pending_manager::write(None);
+
+    common::events::emit(&events::ManagerChanged {
+        previous_manager,
+        manager: caller,
+    });
+}
+
+fn get_pending_manager() -> Option<Key> {
+    state::pending_manager::read()
+}
+
+// Pausing stops royalty payments and the transfers they authorize, e.g. when a whitelisted
+// marketplace is compromised. Pending payments can still be refunded.
+fn set_paused(paused: bool, allow_manager_recovery: bool) {
+    ensure_caller_is_manager();
+
+    state::paused::write(paused);
+    state::manager_recovery_allowed::write(allow_manager_recovery);
+
+    common::events::emit(&events::PauseChanged {
+        paused,
+        allow_manager_recovery,
+    });
+}
+
+fn is_paused() -> bool {
+    state::paused::read()
+}
+
+// Payments must expire, or a payment whose transfer never happens could neither be refunded nor
+// replaced while its source owns the token.
+fn set_payment_expiry(expiry: u64) {
+    ensure_caller_is_manager();
+    ensure!(expiry > 0, CustodialError::InvalidPaymentExpiry);
+
+    state::payment_expiry::write(expiry);
+}
+
+fn set_royalty_structure(royalty_structure: RoyaltyStructure) {
+    ensure_caller_is_manager();
+    royalty_structure.validate();
+
+    state::royalty_structure::write(royalty_structure.clone());
+
+    common::events::emit(&events::RoyaltyStructureChanged {
+        token_contract: None,
+        token_id: None,
+        token_hash: None,
+        royalty_structure: Some(royalty_structure),
+    });
+}
+
+fn set_royalty_recipients(recipients: Vec<RoyaltyRecipient>) {
+    ensure_caller_is_manager();
+
+    let mut royalty_structure = get_royalty_structure();
+    royalty_structure.recipients = recipients;
+    royalty_structure.validate();
+
+    state::royalty_structure::write(royalty_structure.clone());
+
+    common::events::emit(&events::RoyaltyStructureChanged {
+        token_contract: None,
+        token_id: None,
+        token_hash: None,
+        royalty_structure: Some(royalty_structure),
+    });
+}
+
+fn set_royalty_override(
+    token_contract: ContractPackageHash,
+    token_id: Option<TokenIdentifier>,
+    royalty_structure: RoyaltyStructure,
+) {
+    ensure_caller_is_manager();
+    royalty_structure.validate();
+
+    state::set_royalty_override(
+        token_contract,
+        token_id.as_ref(),
+        Some(royalty_structure.clone()),
+    );
+
+    let (token_id, token_hash) = token_id
+        .as_ref()
+        .map_or((None, None), events::token_identifier);
+    common::events::emit(&events::RoyaltyStructureChanged {
+        token_contract: Some(token_contract),
+        token_id,
+        token_hash,
+        royalty_structure: Some(royalty_structure),
+    });
+}
+
+fn remove_royalty_override(token_contract: ContractPackageHash, token_id: Option<TokenIdentifier>) {
+    ensure_caller_is_manager();
+    state::set_royalty_override(token_contract, token_id.as_ref(), None);
+
+    let (token_id, token_hash) = token_id
+        .as_ref()
+        .map_or((None, None), events::token_identifier);
+    common::events::emit(&events::RoyaltyStructureChanged {
+        token_contract: Some(token_contract),
+        token_id,
+        token_hash,
+        royalty_structure: None,
+    });
+}
+
+fn get_royalty_override(
+    token_contract: ContractPackageHash,
+    token_id: Option<TokenIdentifier>,
+) -> Option<RoyaltyStructure> {
+    state::royalty_override(token_contract, token_id.as_ref())
+}
+
+fn get_royalty_structure() -> RoyaltyStructure {
+    o_unwrap!(
         state::royalty_structure::try_read(),
         CustodialError::InvalidRoyaltyScheme
+    )
+}
+
+fn add_whitelisted_marketplace(marketplace: ContractPackageHash) {
+    ensure_caller_is_manager();
+    ensure!(
+        state::add_whitelisted_marketplace(marketplace),
+        CustodialError::MarketplaceAlreadyWhitelisted
+    );
+
+    common::events::emit(&events::MarketplaceWhitelisted { marketplace });
+}
+
+fn remove_whitelisted_marketplace(marketplace: ContractPackageHash) {
+    ensure_caller_is_manager();
+    ensure!(
+        state::remove_whitelisted_marketplace(marketplace),
+        CustodialError::MarketplaceNotWhitelisted
+    );
+
+    common::events::emit(&events::MarketplaceRemovedFromWhitelist { marketplace });
+}
+
+fn set_whitelist_enabled(enabled: bool) {
+    ensure_caller_is_manager();
+    state::marketplace_whitelist_enabled::write(enabled);
+
+    common::events::emit(&events::WhitelistEnabledChanged { enabled });
+}
+
+// Accounts paying royalties directly are subject to the whitelist, just like marketplaces.
+fn set_account_whitelisted(account: Key, whitelisted: bool) {
+    ensure_caller_is_manager();
+    ensure!(
+        matches!(account, Key::Account(_)),
+        CustodialError::CallerMustBeAccount
+    );
+
+    state::set_account_whitelisted(account, whitelisted);
+
+    common::events::emit(&events::AccountWhitelistChanged {
+        account,
+        whitelisted,
+    });
+}
+
+fn is_account_whitelisted(account: Key) -> bool {
+    state::is_account_whitelisted(account)
+}
+
+fn get_whitelisted_marketplaces(offset: u64, limit: u64) -> Vec<ContractPackageHash> {
+    state::whitelisted_marketplaces_page(offset, limit)
+}
+
+fn withdraw_royalties(amount: U512, target: Key, currency: Currency) {
+    ensure_caller_is_manager();
+    ensure!(
+        amount <= royalty_balance(currency),
+        CustodialError::InsufficientBalance
+    );
+
+    pay_out(currency, target, amount);
+
+    state::record_withdrawal(Withdrawal {
+        amount,
+        currency,
+        target,
+        block_time: runtime::get_blocktime().into(),
+    });
+
+    common::events::emit(&events::RoyaltiesWithdrawn {
+        target,
+        amount,
+        currency,
+    });
+}
+
+/// Balance of royalties in `currency` that is neither owed to a royalty recipient nor held for a
+/// pending transfer.
+fn royalty_balance(currency: Currency) -> U512 {
+    let balance = match currency {
+        Some(package) => {
+            from_token_amount(common::ext::erc20::balance_of(package, this_package_key()))
+        }
+        None => contract_api::system::get_purse_balance(royalty_purse()).unwrap_or_revert(),
+    };
+
+    balance
+        .saturating_sub(state::total_claimable_in(currency))
+        .saturating_sub(state::pending_royalties_in(currency))
+}
+
+fn get_withdrawals(offset: u64, limit: u64) -> Vec<Withdrawal> {
+    state::withdrawals_page(offset, limit)
+}
+
+fn claim(target: Key, currency: Currency) {
+    let recipient = common::call_stack::caller().key();
+    let amount = state::take_claimable(recipient, currency);
+    ensure!(!amount.is_zero(), CustodialError::NothingToClaim);
+
+    pay_out(currency, target, amount);
+
+    common::events::emit(&events::RoyaltyClaimed {
+        recipient,
+        target,
+        amount,
+        currency,
+    });
+}
+
+fn claimable_balance(recipient: Key, currency: Currency) -> U512 {
+    state::claimable_balance(recipient, currency)
+}
+
+fn set_currency_accepted(currency: ContractPackageHash, accepted: bool) {
+    ensure_caller_is_manager();
+    state::set_currency_accepted(currency, accepted);
+}
+
+fn get_royalty_currency(
+    token_contract: ContractPackageHash,
+    token_id: TokenIdentifier,
+    source_key: Option<Key>,
+) -> Currency {
+    resolve_royalty_structure(token_contract, &token_id, source_key).currency
+}
+
+fn add_creator(creator: Key) {
+    ensure_caller_is_manager();
+    ensure!(
+        !state::is_creator(creator),
+        CustodialError::CreatorAlreadyRegistered
+    );
+
+    state::set_creator(creator, true);
+
+    common::events::emit(&events::CreatorAdded { creator });
+}
+
+fn remove_creator(creator: Key) {
+    ensure_caller_is_manager();
+    ensure!(
+        state::is_creator(creator),
+        CustodialError::CreatorNotRegistered
+    );
+
+    state::set_creator(creator, false);
+
+    common::events::emit(&events::CreatorRemoved { creator });
+}
+
+fn is_creator(creator: Key) -> bool {
+    state::is_creator(creator)
+}
+
+fn set_primary_sale_structure(royalty_structure: RoyaltyStructure) {
+    ensure_caller_is_manager();
+    royalty_structure.validate();
+
+    state::primary_sale_structure::write(Some(royalty_structure.clone()));
+
+    common::events::emit(&events::PrimarySaleStructureChanged {
+        royalty_structure: Some(royalty_structure),
+    });
+}
+
+fn remove_primary_sale_structure() {
+    ensure_caller_is_manager();
+    state::primary_sale_structure::write(None);
+
+    common::events::emit(&events::PrimarySaleStructureChanged {
+        royalty_structure: None,
+    });
+}
+
+fn get_primary_sale_structure() -> Option<RoyaltyStructure> {
+    state::primary_sale_structure::read()
+}
+
+fn set_first_sale_primary(first_sale_primary: bool) {
+    ensure_caller_is_manager();
+    state::first_sale_primary::write(first_sale_primary);
+}
+
+fn is_first_sale_primary() -> bool {
+    state::first_sale_primary::read()
+}
+
+fn get_payment_state(
+    token_contract: ContractPackageHash,
+    token_id: TokenIdentifier,
+) -> RoyaltyPaymentState {
+    state::read_payment_state(token_contract, &token_id)
+}
+
+/// Whether a transfer of the token from `source_key` to `target_key` would be authorized by the
+/// transfer filter right now.
+fn is_transfer_authorized(
+    token_contract: ContractPackageHash,
+    token_id: TokenIdentifier,
+    source_key: Key,
+    target_key: Key,
+) -> bool {
+    if state::paused::read() {
+        return false;
+    }
+
+    if state::is_transfer_exempt(source_key, target_key) {
+        return true;
+    }
+
+    let RoyaltyPaymentState::Paid {
+        source_key: payment_source_key,
+        target_key: payment_target_key,
+        paid_at,
+        ..
+    } = state::read_payment_state(token_contract, &token_id) else {
+        return false;
+    };
+
+    !state::is_payment_expired(paid_at)
+        && source_key == payment_source_key
+        && target_key == payment_target_key
+        && source_key == common::ext::cep78::owner_of(token_contract, &token_id)
+}
+
+fn get_settled_payment_count(
+    token_contract: ContractPackageHash,
+    token_id: TokenIdentifier,
+) -> u64 {
+    state::settled_payment_count(token_contract, &token_id)
+}
+
+fn get_settled_payments(
+    token_contract: ContractPackageHash,
+    token_id: TokenIdentifier,
+    offset: u64,
+    limit: u64,
+) -> Vec<SettledPayment> {
+    state::settled_payments_page(token_contract, &token_id, offset, limit)
+}
+
+fn get_collection_stats(token_contract: ContractPackageHash, currency: Currency) -> RoyaltyStats {
+    state::collection_stats_of(token_contract, currency)
+}
+
+fn get_marketplace_stats(marketplace: Key, currency: Currency) -> RoyaltyStats {
+    state::marketplace_stats_of(marketplace, currency)
+}
+
+fn is_marketplace_whitelisted(marketplace: ContractPackageHash) -> bool {
+    state::is_marketplace_whitelisted(marketplace)
+}
+
+fn is_whitelist_enabled() -> bool {
+    state::marketplace_whitelist_enabled::read()
+}
+
+fn get_manager() -> Key {
+    state::manager::read()
+}
+
+// Pay out `amount` of the royalties held by this contract in `currency`. CSPR can only be paid to
+// an account or a purse, see `transfer_from_purse`.
+fn pay_out(currency: Currency, target: Key, amount: U512) {
+    match currency {
+        Some(package) => common::ext::erc20::transfer(package, target, to_token_amount(amount)),
+        None => transfer_from_purse(royalty_purse(), target, amount),
+    }
+}
+
+fn this_package_key() -> Key {
+    Key::Hash(common::call_stack::current_package().value())
+}
+
+// Royalties are computed as `U512` regardless of their currency, while CEP-18 amounts are `U256`.
+fn to_token_amount(amount: U512) -> U256 {
+    let mut bytes = [0u8; 64];
+    amount.to_little_endian(&mut bytes);
+    ensure!(
+        bytes[32..].iter().all(|byte| *byte == 0),
+        CustodialError::Overflow
     );
-    royalty_structure.calculate_total_royalty(payment_amount)
+
+    U256::from_little_endian(&bytes[..32])
+}
+
+fn from_token_amount(amount: U256) -> U512 {
+    let mut bytes = [0u8; 64];
+    amount.to_little_endian(&mut bytes[..32]);
+
+    U512::from_little_endian(&bytes)
+}
+
+fn royalty_purse() -> URef {
+    runtime::get_key(NK_ROYALTY_PURSE)
+        .unwrap_or_revert()
+        .into_uref()
+        .unwrap_or_revert()
 }
 
-fn can_transfer(token_id: TokenIdentifier, source_key: Key, target_key: Key) -> u8 {
+/// Move `amount` out of `purse`, either to the main purse of an account (`Key::Account`) or to
+/// another purse (`Key::URef`).
+fn transfer_from_purse(purse: URef, target: Key, amount: U512) {
+    match target {
+        Key::Account(account_hash) => {
+            r_unwrap!(
+                contract_api::system::transfer_from_purse_to_account(
+                    purse,
+                    account_hash,
+                    amount,
+                    None
+                ),
+                CustodialError::TransferFailed
+            );
+        }
+        Key::URef(target_purse) => {
+            r_unwrap!(
+                contract_api::system::transfer_from_purse_to_purse(
+                    purse,
+                    target_purse,
+                    amount,
+                    None
+                ),
+                CustodialError::TransferFailed
+            );
+        }
+        _ => revert(CustodialError::InvalidTransferTarget),
+    }
+}
+
+fn ensure_caller_is_manager() {
+    let caller = common::call_stack::caller().key();
+    ensure_eq!(
+        caller,
+        state::manager::read(),
+        CustodialError::CallerMustBeManager
+    );
+}
+
+fn calculate_royalty(
+    token_contract: ContractPackageHash,
+    token_id: TokenIdentifier,
+    payment_amount: U512,
+    source_key: Option<Key>,
+) -> U512 {
+    let royalty_structure = resolve_royalty_structure(token_contract, &token_id, source_key);
+    let sale = sale_context(token_contract, &token_id, &royalty_structure);
+    royalty_structure.calculate_total_royalty(payment_amount, &sale)
+}
+
+fn quote_royalty(
+    token_contract: ContractPackageHash,
+    token_id: TokenIdentifier,
+    payment_amount: U512,
+    source_key: Option<Key>,
+) -> RoyaltyQuote {
+    let royalty_structure = resolve_royalty_structure(token_contract, &token_id, source_key);
+    let sale = sale_context(token_contract, &token_id, &royalty_structure);
+    royalty_structure.quote(payment_amount, &sale)
+}
+
+// Sale prices in different currencies cannot be compared, so the last sale price of a token is
+// only known if it was paid in the currency of its royalty structure.
+fn sale_context(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+    royalty_structure: &RoyaltyStructure,
+) -> SaleContext {
+    let last_sale_price = state::last_settled_payment(token_contract, token_id)
+        .filter(|payment| payment.currency == royalty_structure.currency)
+        .map(|payment| payment.sale_price);
+
+    SaleContext {
+        block_time: runtime::get_blocktime().into(),
+        last_sale_price,
+    }
+}
+
+// Primary sales use the primary-sale structure, if one is set. Otherwise the royalty structure of
+// a token is its own override if it has one, then the override of its collection, and finally the
+// default royalty structure of the custodial contract.
+fn resolve_royalty_structure(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+    source_key: Option<Key>,
+) -> RoyaltyStructure {
+    if is_primary_sale(token_contract, token_id, source_key) {
+        if let Some(royalty_structure) = state::primary_sale_structure::read() {
+            return royalty_structure;
+        }
+    }
+
+    state::royalty_override(token_contract, Some(token_id))
+        .or_else(|| state::royalty_override(token_contract, None))
+        .unwrap_or_else(get_royalty_structure)
+}
+
+// A sale is a primary sale when it is made by a registered creator, or when it is the first sale of
+// the token recorded by this contract and the manager has not disabled that rule.
+fn is_primary_sale(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+    source_key: Option<Key>,
+) -> bool {
+    source_key.map_or(false, state::is_creator)
+        || (state::first_sale_primary::read()
+            && state::settled_payment_count(token_contract, token_id) == 0)
+}
+
+// Only the transfer filter may authorize transfers, since it vouches for `token_contract` being the
+// collection that is transferring the token.
+fn authorize_transfer(
+    token_contract: ContractPackageHash,
+    token_id: TokenIdentifier,
+    source_key: Key,
+    target_key: Key,
+) -> u8 {
     const PROCEED: u8 = 1;
     const DENY: u8 = 0;
 
-    let token_contract = o_unwrap!(
-        common::call_stack::caller().contract_package(),
-        CustodialError::CallerMustBeContract
+    ensure_eq!(
+        common::call_stack::caller().contract(),
+        Some(state::transfer_filter::read()),
+        CustodialError::CallerMustBeTransferFilter
     );
 
-    let key = token_id.to_key();
-    let payment_state = state::royalty_payments::read(&key);
+    if state::paused::read() {
+        // Only the manager may still transfer tokens it controls, and only when it allowed itself
+        // to. Such transfers do not settle any royalty payment. The manager calls the collection,
+        // which calls the filter, which calls this contract.
+        let initiator = common::call_stack::at_depth(3).map(|element| element.key());
+        if state::manager_recovery_allowed::read() && initiator == Some(state::manager::read()) {
+            let (token_id, token_hash) = events::token_identifier(&token_id);
+            common::events::emit(&events::TransferAuthorized {
+                token_contract,
+                token_id,
+                token_hash,
+                source_key,
+                target_key,
+            });
+
+            return PROCEED;
+        }
+
+        return DENY;
+    }
+
+    // Exempt transfers need no payment, and leave any pending payment to be refunded.
+    if state::is_transfer_exempt(source_key, target_key) {
+        let (token_id, token_hash) = events::token_identifier(&token_id);
+        common::events::emit(&events::TransferAuthorized {
+            token_contract,
+            token_id,
+            token_hash,
+            source_key,
+            target_key,
+        });
 
-    let RoyaltyPaymentState::Paid { source_key: payment_key, .. } = payment_state else {
+        return PROCEED;
+    }
+
+    let payment_state = state::read_payment_state(token_contract, &token_id);
+
+    let RoyaltyPaymentState::Paid {
+        payer,
+        source_key: payment_source_key,
+        target_key: payment_target_key,
+        marketplace,
+        sale_price,
+        amount,
+        currency,
+        paid_at,
+        sale,
+    } = payment_state else {
         casper_contract::contract_api::runtime::revert(CustodialError::MustPayRoyalties);
     };
 
+    ensure!(
+        !state::is_payment_expired(paid_at),
+        CustodialError::MustPayRoyalties
+    );
+
     let current_owner = common::ext::cep78::owner_of(token_contract, &token_id);
 
-    if source_key == payment_key && source_key == current_owner {
-        state::royalty_payments::write(&key, RoyaltyPaymentState::Unpaid);
-        
+    if source_key == payment_source_key
+        && target_key == payment_target_key
+        && source_key == current_owner
+    {
+        state::write_payment_state(token_contract, &token_id, RoyaltyPaymentState::Unpaid);
+        settle_royalty(token_contract, &token_id, source_key, amount, currency);
+        // Gifts have no sale price, so they would corrupt the last sale price of the token.
+        if sale {
+            state::record_settled_payment(
+                token_contract,
+                &token_id,
+                SettledPayment {
+                    payer,
+                    source_key,
+                    target_key,
+                    marketplace,
+                    sale_price,
+                    amount,
+                    currency,
+                    block_time: runtime::get_blocktime().into(),
+                },
+            );
+        }
+
+        let (token_id, token_hash) = events::token_identifier(&token_id);
+        common::events::emit(&events::TransferAuthorized {
+            token_contract,
+            token_id,
+            token_hash,
+            source_key,
+            target_key,
+        });
+
         return PROCEED;
     }
 
     return DENY;
-}
\ No newline at end of file
+}
+
+// A paid royalty is only owed to the royalty recipients once the transfer it was paid for goes
+// through. Until then it can be refunded to the payer.
+fn settle_royalty(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+    source_key: Key,
+    amount: U512,
+    currency: Currency,
+) {
+    state::release_pending_royalties(currency, amount);
+
+    let royalty_structure = resolve_royalty_structure(token_contract, token_id, Some(source_key));
+    for (recipient, share) in royalty_structure.split(amount) {
+        state::credit_claimable(recipient, currency, share);
+    }
+}
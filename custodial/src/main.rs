@@ -4,6 +4,8 @@
 use common::forward_entrypoints;
 #[allow(unused)]
 use custodial::entry_point as ep;
+#[allow(unused)]
+use custodial::entry_point::filter as filter_ep;
 
 forward_entrypoints! {
     ep: [ call ]
@@ -11,8 +13,68 @@ forward_entrypoints! {
 
 forward_entrypoints! {
     ep: [
+        migrate,
         calculate_royalty,
-        can_transfer,
+        quote_royalty,
+        get_royalty_currency,
+        authorize_transfer,
+        get_transfer_filter,
+        is_transfer_authorized,
+        get_payment_state,
+        get_settled_payment_count,
+        get_settled_payments,
+        get_collection_stats,
+        get_marketplace_stats,
         pay_royalty,
+        pay_royalty_cep18,
+        pay_royalty_direct,
+        refund_royalty,
+        pay_gift_fee,
+        set_gift_fee,
+        get_gift_fee,
+        set_transfer_exemption,
+        set_account_group,
+        get_account_group,
+        is_transfer_exempt,
+        get_manager,
+        propose_manager,
+        accept_manager,
+        get_pending_manager,
+        set_paused,
+        is_paused,
+        set_payment_expiry,
+        set_royalty_structure,
+        get_royalty_structure,
+        set_royalty_recipients,
+        set_royalty_override,
+        remove_royalty_override,
+        get_royalty_override,
+        add_creator,
+        remove_creator,
+        is_creator,
+        set_primary_sale_structure,
+        remove_primary_sale_structure,
+        get_primary_sale_structure,
+        set_first_sale_primary,
+        is_first_sale_primary,
+        add_whitelisted_marketplace,
+        remove_whitelisted_marketplace,
+        set_whitelist_enabled,
+        is_whitelist_enabled,
+        is_marketplace_whitelisted,
+        set_account_whitelisted,
+        is_account_whitelisted,
+        get_whitelisted_marketplaces,
+        withdraw_royalties,
+        royalty_balance,
+        get_withdrawals,
+        claim,
+        claimable_balance,
+        set_currency_accepted,
     ]
 }
+
+// The transfer filter is installed from this same module, see `custodial::filter`.
+forward_entrypoints! {
+    filter_ep: [ can_transfer ]
+}
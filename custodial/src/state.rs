@@ -1,16 +1,149 @@
-use common::{prelude::*, FromNamedArg, ToStrKey};
+use common::{o_unwrap, prelude::*, r_unwrap, token::TokenIdentifier, FromNamedArg, ToStrKey};
 use num_traits::AsPrimitive;
 
 use crate::CustodialError;
 
+/// Percentages in royalty steps are expressed in basis points.
+pub const BASIS_POINTS: u64 = 10000;
+
+/// Version of the state layout of this contract version. Contracts installed before the state was
+/// versioned are at version 0.
+pub const STATE_VERSION: u64 = 9;
+
+/// Time after which an unsettled royalty payment expires, in milliseconds of block time, unless the
+/// manager sets another one: a day.
+pub const DEFAULT_PAYMENT_EXPIRY: u64 = 24 * 60 * 60 * 1000;
+
+/// The currency royalties are paid in: a CEP-18 token package, or CSPR if `None`.
+///
+/// Entry points taking a currency expect the package hash of the token as the argument, and CSPR
+/// when the argument is omitted.
+pub type Currency = Option<ContractPackageHash>;
+
 named_keys! {
-    init_all(manager: Key, royalty_structure: RoyaltyStructure):
+    init_all(manager: Key, royalty_structure: RoyaltyStructure, transfer_filter: ContractHash):
     dict whitelisted_marketplaces: bool;
+    dict whitelisted_marketplace_by_index: ContractPackageHash;
+    dict whitelisted_marketplace_index: u64;
+    dict whitelisted_accounts: bool;
     dict royalty_payments: RoyaltyPaymentState;
+    dict withdrawals: Withdrawal;
+    dict settled_payments: SettledPayment;
+    dict settled_payment_counts: u64;
+    dict collection_stats: RoyaltyStats;
+    dict marketplace_stats: RoyaltyStats;
+    dict claimable_balances: U512;
+    dict total_claimable: U512;
+    dict pending_royalties: U512;
+    dict royalty_overrides: Option<RoyaltyStructure>;
+    dict accepted_currencies: bool;
+    dict creators: bool;
+    dict transfer_exemptions: bool;
+    dict account_groups: Option<u64>;
 
     val marketplace_whitelist_enabled: bool = false;
+    val whitelisted_marketplace_count: u64 = 0;
+    val withdrawal_count: u64 = 0;
+    val payment_expiry: u64 = DEFAULT_PAYMENT_EXPIRY;
+    val state_version: u64 = STATE_VERSION;
+    val paused: bool = false;
+    val manager_recovery_allowed: bool = false;
     val manager: Key = manager;
+    val pending_manager: Option<Key> = None;
     val royalty_structure: RoyaltyStructure = royalty_structure;
+    val primary_sale_structure: Option<RoyaltyStructure> = None;
+    val first_sale_primary: bool = true;
+    val gift_fee: Option<U512> = None;
+    val transfer_filter: ContractHash = transfer_filter;
+}
+
+// Create the named keys that are missing from the contract, with their initial values.
+macro_rules! init_missing {
+    ($( $name:ident $(= $init:expr)? ),* $(,)?) => {{
+        $(
+            if runtime::get_key($name::NAME).is_none() {
+                $name::put_uref($name::init($($init)?));
+            }
+        )*
+    }};
+}
+
+/// Migrate the state of the contract to [`STATE_VERSION`], one version at a time. Migrating a
+/// contract that is already up to date does nothing. Contracts installed without a transfer filter
+/// are given `transfer_filter`.
+pub fn migrate(transfer_filter: ContractHash) {
+    let mut version = match runtime::get_key(state_version::NAME) {
+        Some(_) => state_version::read(),
+        None => 0,
+    };
+
+    while version < STATE_VERSION {
+        match version {
+            0 => migrate_from_unversioned(),
+            1 => init_missing!(pending_manager = None),
+            2 => init_missing!(paused = false, manager_recovery_allowed = false),
+            3 => init_missing!(creators, primary_sale_structure = None),
+            4 => init_missing!(transfer_exemptions, account_groups, gift_fee = None),
+            5 => init_missing!(whitelisted_accounts),
+            6 => migrate_payment_expiry(),
+            7 => init_missing!(transfer_filter = transfer_filter),
+            // Tokens may have been sold before the contract kept a ledger of sales.
+            8 => init_missing!(first_sale_primary = false),
+            _ => revert(ApiError::InvalidArgument),
+        }
+
+        version += 1;
+    }
+
+    state_version::write(version);
+}
+
+// Contracts installed before the state was versioned lack most of the named keys, and store their
+// royalty structure without recipients or currency.
+//
+// Marketplaces whitelisted by such contracts are still whitelisted, but since the dictionary cannot
+// be enumerated, they cannot be indexed here. They are not listed by `get_whitelisted_marketplaces`
+// until they are added again, which indexes them, and can be removed either way.
+fn migrate_from_unversioned() {
+    init_missing!(
+        whitelisted_marketplace_by_index,
+        whitelisted_marketplace_index,
+        withdrawals,
+        settled_payments,
+        settled_payment_counts,
+        collection_stats,
+        marketplace_stats,
+        claimable_balances,
+        total_claimable,
+        pending_royalties,
+        royalty_overrides,
+        accepted_currencies,
+        whitelisted_marketplace_count = 0,
+        withdrawal_count = 0,
+        payment_expiry = 0,
+        state_version = 0,
+    );
+
+    // A structure that does not parse in the legacy layout is already in the current one.
+    let legacy_royalty_structure: Option<LegacyRoyaltyStructure> =
+        storage::read(royalty_structure::uref()).ok().flatten();
+    if let Some(LegacyRoyaltyStructure(structure)) = legacy_royalty_structure {
+        royalty_structure::write(structure);
+    }
+}
+
+// Payments used to never expire by default, which left a payment whose transfer never happened
+// standing forever, and its source unable to pay for any other transfer.
+fn migrate_payment_expiry() {
+    if payment_expiry::read() == 0 {
+        payment_expiry::write(DEFAULT_PAYMENT_EXPIRY);
+    }
+}
+
+/// Managers are either accounts, or contract packages (as [`Key::Hash`]) such as a DAO or a
+/// multisig contract.
+pub fn is_valid_manager(manager: Key) -> bool {
+    matches!(manager, Key::Account(_) | Key::Hash(_))
 }
 
 pub fn is_marketplace_whitelisted(marketplace: ContractPackageHash) -> bool {
@@ -18,6 +151,371 @@ pub fn is_marketplace_whitelisted(marketplace: ContractPackageHash) -> bool {
     whitelisted_marketplaces::try_read(&marketplace_key).unwrap_or(false)
 }
 
+/// Add a marketplace to the whitelist. Returns `false` if it was already whitelisted, unless it was
+/// whitelisted before the whitelist was indexed, in which case it is indexed now.
+pub fn add_whitelisted_marketplace(marketplace: ContractPackageHash) -> bool {
+    let marketplace_key = marketplace.to_key();
+    if is_marketplace_whitelisted(marketplace)
+        && whitelisted_marketplace_index::try_read(&marketplace_key).is_some()
+    {
+        return false;
+    }
+
+    let index = whitelisted_marketplace_count::read();
+
+    whitelisted_marketplaces::write(&marketplace_key, true);
+    whitelisted_marketplace_index::write(&marketplace_key, index);
+    whitelisted_marketplace_by_index::write(&index.to_key(), marketplace);
+    whitelisted_marketplace_count::write(index + 1);
+
+    true
+}
+
+/// Remove a marketplace from the whitelist. Returns `false` if it was not whitelisted.
+///
+/// The last marketplace in the listing takes the place of the removed one, so the listing order
+/// is not stable across removals.
+pub fn remove_whitelisted_marketplace(marketplace: ContractPackageHash) -> bool {
+    if !is_marketplace_whitelisted(marketplace) {
+        return false;
+    }
+
+    let marketplace_key = marketplace.to_key();
+    whitelisted_marketplaces::write(&marketplace_key, false);
+
+    // Marketplaces whitelisted before the whitelist was indexed were never listed nor counted.
+    let Some(index) = whitelisted_marketplace_index::try_read(&marketplace_key) else {
+        return true;
+    };
+    let last_index = whitelisted_marketplace_count::read() - 1;
+
+    if index != last_index {
+        let last_marketplace = whitelisted_marketplace_by_index::read(&last_index.to_key());
+        whitelisted_marketplace_by_index::write(&index.to_key(), last_marketplace);
+        whitelisted_marketplace_index::write(&last_marketplace.to_key(), index);
+    }
+
+    whitelisted_marketplace_by_index::remove(&last_index.to_key());
+    whitelisted_marketplace_count::write(last_index);
+
+    true
+}
+
+pub fn whitelisted_marketplaces_page(offset: u64, limit: u64) -> Vec<ContractPackageHash> {
+    let end = offset
+        .saturating_add(limit)
+        .min(whitelisted_marketplace_count::read());
+
+    (offset..end)
+        .map(|index| whitelisted_marketplace_by_index::read(&index.to_key()))
+        .collect()
+}
+
+pub fn record_withdrawal(withdrawal: Withdrawal) {
+    let index = withdrawal_count::read();
+    withdrawals::write(&index.to_key(), withdrawal);
+    withdrawal_count::write(index + 1);
+}
+
+pub fn withdrawals_page(offset: u64, limit: u64) -> Vec<Withdrawal> {
+    let end = offset.saturating_add(limit).min(withdrawal_count::read());
+
+    (offset..end)
+        .map(|index| withdrawals::read(&index.to_key()))
+        .collect()
+}
+
+pub fn is_creator(creator: Key) -> bool {
+    creators::try_read(&creator.to_key()).unwrap_or(false)
+}
+
+pub fn set_creator(creator: Key, registered: bool) {
+    creators::write(&creator.to_key(), registered);
+}
+
+pub fn set_transfer_exemption(source_key: Key, target_key: Key, exempt: bool) {
+    transfer_exemptions::write(&(source_key, target_key).to_key(), exempt);
+}
+
+pub fn account_group(account: Key) -> Option<u64> {
+    account_groups::try_read(&account.to_key()).flatten()
+}
+
+pub fn set_account_group(account: Key, group: Option<u64>) {
+    account_groups::write(&account.to_key(), group);
+}
+
+/// Transfers need no royalty payment between an exempt pair of keys, or between two accounts of
+/// the same group, such as the accounts of a single user.
+pub fn is_transfer_exempt(source_key: Key, target_key: Key) -> bool {
+    if transfer_exemptions::try_read(&(source_key, target_key).to_key()).unwrap_or(false) {
+        return true;
+    }
+
+    match (account_group(source_key), account_group(target_key)) {
+        (Some(source_group), Some(target_group)) => source_group == target_group,
+        _ => false,
+    }
+}
+
+pub fn is_account_whitelisted(account: Key) -> bool {
+    whitelisted_accounts::try_read(&account.to_key()).unwrap_or(false)
+}
+
+pub fn set_account_whitelisted(account: Key, whitelisted: bool) {
+    whitelisted_accounts::write(&account.to_key(), whitelisted);
+}
+
+pub fn is_currency_accepted(currency: ContractPackageHash) -> bool {
+    accepted_currencies::try_read(&currency.to_key()).unwrap_or(false)
+}
+
+pub fn set_currency_accepted(currency: ContractPackageHash, accepted: bool) {
+    accepted_currencies::write(&currency.to_key(), accepted);
+}
+
+fn currency_key(currency: Currency) -> String {
+    match currency {
+        Some(package) => package.to_key(),
+        None => "cspr".into(),
+    }
+}
+
+pub fn claimable_balance(recipient: Key, currency: Currency) -> U512 {
+    claimable_balances::try_read(&(recipient, currency).to_key()).unwrap_or_default()
+}
+
+/// Total amount owed to all royalty recipients in `currency`.
+pub fn total_claimable_in(currency: Currency) -> U512 {
+    total_claimable::try_read(&currency_key(currency)).unwrap_or_default()
+}
+
+pub fn credit_claimable(recipient: Key, currency: Currency, amount: U512) {
+    let balance = claimable_balance(recipient, currency)
+        .checked_add(amount)
+        .unwrap_or_revert_with(CustodialError::Overflow);
+    let total = total_claimable_in(currency)
+        .checked_add(amount)
+        .unwrap_or_revert_with(CustodialError::Overflow);
+
+    claimable_balances::write(&(recipient, currency).to_key(), balance);
+    total_claimable::write(&currency_key(currency), total);
+}
+
+/// Reset the claimable balance of `recipient`, returning the amount that was owed to it.
+pub fn take_claimable(recipient: Key, currency: Currency) -> U512 {
+    let amount = claimable_balance(recipient, currency);
+
+    claimable_balances::write(&(recipient, currency).to_key(), U512::zero());
+    total_claimable::write(
+        &currency_key(currency),
+        total_claimable_in(currency) - amount,
+    );
+
+    amount
+}
+
+/// Total amount of royalties in `currency` paid for transfers that did not happen yet.
+pub fn pending_royalties_in(currency: Currency) -> U512 {
+    pending_royalties::try_read(&currency_key(currency)).unwrap_or_default()
+}
+
+pub fn reserve_pending_royalties(currency: Currency, amount: U512) {
+    let pending = pending_royalties_in(currency)
+        .checked_add(amount)
+        .unwrap_or_revert_with(CustodialError::Overflow);
+    pending_royalties::write(&currency_key(currency), pending);
+}
+
+// Legacy payments were never reserved, so releasing them saturates.
+pub fn release_pending_royalties(currency: Currency, amount: U512) {
+    let pending = pending_royalties_in(currency).saturating_sub(amount);
+    pending_royalties::write(&currency_key(currency), pending);
+}
+
+/// Whether a payment made at `paid_at` has expired.
+pub fn is_payment_expired(paid_at: u64) -> bool {
+    let now: u64 = runtime::get_blocktime().into();
+
+    now >= paid_at.saturating_add(payment_expiry::read())
+}
+
+// Payment states are namespaced by the token contract, so that a single custodial contract can
+// serve several collections.
+fn payment_state_key(token_contract: ContractPackageHash, token_id: &TokenIdentifier) -> String {
+    (token_contract, token_id.clone()).to_key()
+}
+
+/// Read the royalty payment state of a token.
+///
+/// Payment states used to be keyed by the token identifier alone. A state still stored under such
+/// a legacy key is moved to the namespaced key of `token_contract` the first time it is read.
+/// Legacy payments were not bound to a transfer target, see [`LegacyRoyaltyPaymentState`].
+pub fn read_payment_state(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+) -> RoyaltyPaymentState {
+    let key = payment_state_key(token_contract, token_id);
+    if let Some(payment_state) = royalty_payments::try_read(&key) {
+        return payment_state;
+    }
+
+    let legacy_key = token_id.to_key();
+    let legacy_payment_state: Option<LegacyRoyaltyPaymentState> = r_unwrap!(
+        storage::dictionary_get(royalty_payments::uref(), &legacy_key),
+        ApiError::Deserialize
+    );
+    match legacy_payment_state {
+        Some(LegacyRoyaltyPaymentState(payment_state)) => {
+            storage::dictionary_put(
+                royalty_payments::uref(),
+                &legacy_key,
+                LegacyRoyaltyPaymentState(RoyaltyPaymentState::Unpaid),
+            );
+            royalty_payments::write(&key, payment_state.clone());
+            payment_state
+        }
+        None => RoyaltyPaymentState::Unpaid,
+    }
+}
+
+/// Append a payment to the ledger of the token it was settled for.
+pub fn record_settled_payment(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+    payment: SettledPayment,
+) {
+    let key = payment_state_key(token_contract, token_id);
+    let index = settled_payment_counts::try_read(&key).unwrap_or_default();
+    settled_payments::write(&(key.clone(), index).to_key(), payment);
+    settled_payment_counts::write(&key, index + 1);
+}
+
+pub fn settled_payment_count(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+) -> u64 {
+    settled_payment_counts::try_read(&payment_state_key(token_contract, token_id))
+        .unwrap_or_default()
+}
+
+/// The last settled payment of a token, whose sale price is the last sale price of the token.
+pub fn last_settled_payment(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+) -> Option<SettledPayment> {
+    let index = settled_payment_count(token_contract, token_id).checked_sub(1)?;
+    let key = payment_state_key(token_contract, token_id);
+    settled_payments::try_read(&(key, index).to_key())
+}
+
+pub fn settled_payments_page(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+    offset: u64,
+    limit: u64,
+) -> Vec<SettledPayment> {
+    let key = payment_state_key(token_contract, token_id);
+    let end = offset
+        .saturating_add(limit)
+        .min(settled_payment_count(token_contract, token_id));
+
+    (offset..end)
+        .map(|index| settled_payments::read(&(key.clone(), index).to_key()))
+        .collect()
+}
+
+pub fn collection_stats_of(
+    token_contract: ContractPackageHash,
+    currency: Currency,
+) -> RoyaltyStats {
+    collection_stats::try_read(&(token_contract, currency).to_key()).unwrap_or_default()
+}
+
+pub fn marketplace_stats_of(marketplace: Key, currency: Currency) -> RoyaltyStats {
+    marketplace_stats::try_read(&(marketplace, currency).to_key()).unwrap_or_default()
+}
+
+/// Count a royalty payment towards the statistics of its collection and of its marketplace.
+pub fn add_royalty_stats(
+    token_contract: ContractPackageHash,
+    marketplace: Key,
+    currency: Currency,
+    sale_price: U512,
+    amount: U512,
+) {
+    let update = |stats: RoyaltyStats| RoyaltyStats {
+        payment_count: stats.payment_count + 1,
+        sale_volume: stats.sale_volume.saturating_add(sale_price),
+        royalty_amount: stats.royalty_amount.saturating_add(amount),
+    };
+
+    let key = (token_contract, currency).to_key();
+    collection_stats::write(&key, update(collection_stats_of(token_contract, currency)));
+
+    let key = (marketplace, currency).to_key();
+    marketplace_stats::write(&key, update(marketplace_stats_of(marketplace, currency)));
+}
+
+/// Take a refunded royalty payment back out of the statistics it was counted towards.
+pub fn remove_royalty_stats(
+    token_contract: ContractPackageHash,
+    marketplace: Key,
+    currency: Currency,
+    sale_price: U512,
+    amount: U512,
+) {
+    let update = |stats: RoyaltyStats| RoyaltyStats {
+        payment_count: stats.payment_count.saturating_sub(1),
+        sale_volume: stats.sale_volume.saturating_sub(sale_price),
+        royalty_amount: stats.royalty_amount.saturating_sub(amount),
+    };
+
+    let key = (token_contract, currency).to_key();
+    collection_stats::write(&key, update(collection_stats_of(token_contract, currency)));
+
+    let key = (marketplace, currency).to_key();
+    marketplace_stats::write(&key, update(marketplace_stats_of(marketplace, currency)));
+}
+
+pub fn write_payment_state(
+    token_contract: ContractPackageHash,
+    token_id: &TokenIdentifier,
+    payment_state: RoyaltyPaymentState,
+) {
+    royalty_payments::write(&payment_state_key(token_contract, token_id), payment_state);
+}
+
+// Collection overrides are keyed by the token contract alone, token overrides by the token
+// contract and the token identifier.
+fn royalty_override_key(
+    token_contract: ContractPackageHash,
+    token_id: Option<&TokenIdentifier>,
+) -> String {
+    match token_id {
+        Some(token_id) => (token_contract, token_id.clone()).to_key(),
+        None => token_contract.to_key(),
+    }
+}
+
+pub fn royalty_override(
+    token_contract: ContractPackageHash,
+    token_id: Option<&TokenIdentifier>,
+) -> Option<RoyaltyStructure> {
+    royalty_overrides::try_read(&royalty_override_key(token_contract, token_id)).flatten()
+}
+
+pub fn set_royalty_override(
+    token_contract: ContractPackageHash,
+    token_id: Option<&TokenIdentifier>,
+    royalty_structure: Option<RoyaltyStructure>,
+) {
+    royalty_overrides::write(
+        &royalty_override_key(token_contract, token_id),
+        royalty_structure,
+    );
+}
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RoyaltyPaymentState {
@@ -25,35 +523,366 @@ pub enum RoyaltyPaymentState {
     Paid {
         payer: Key,
         source_key: Key,
+        target_key: Key,
+        marketplace: Key,
+        sale_price: U512,
         amount: U512,
+        currency: Currency,
+        paid_at: u64,
+        /// Whether the payment is for a sale, which counts towards the royalty statistics and is
+        /// recorded in the ledger of the token once settled. Gifts and legacy payments are not.
+        sale: bool,
     },
 }
 
+/// A royalty payment state in the layout used before payments were bound to a transfer target.
+///
+/// Legacy payments are bound to their own source key, which can never be the target of a transfer,
+/// so they no longer authorize any transfer. They are considered paid at time zero for an unknown
+/// sale price of zero, so they have already expired and can be refunded. They are not taken to be
+/// sales.
+pub struct LegacyRoyaltyPaymentState(pub RoyaltyPaymentState);
+
+/// A royalty structure in the layout used before royalties were split between recipients and
+/// priced in a currency. Legacy structures have no recipients and are priced in CSPR.
+pub struct LegacyRoyaltyStructure(pub RoyaltyStructure);
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RoyaltyStep {
-    Minimum { amount: U512 },
-    Flat { amount: U512 },
-    Percentage { percent: U256 },
+    /// Raises the whole royalty to at least `amount`, wherever the step appears in the structure.
+    /// Like any royalty, it is still capped at the payment.
+    Minimum {
+        amount: U512,
+    },
+    Flat {
+        amount: U512,
+    },
+    Percentage {
+        percent: U256,
+    },
+    /// Percentage of the part of the payment that falls between `from` and `to`, or above `from`
+    /// if `to` is `None`. Consecutive brackets describe marginal rates.
+    Bracket {
+        from: U512,
+        to: Option<U512>,
+        percent: U256,
+    },
+    /// Caps the royalty accumulated by the preceding steps.
+    Maximum {
+        amount: U512,
+    },
+    /// Percentage of the payment picked by the block time of the sale, from the first window
+    /// that contains it. Nothing is charged outside of the windows.
+    Scheduled {
+        windows: Vec<RoyaltyWindow>,
+    },
+    /// Percentage of the seller's gain over the last sale price of the token. Tokens without a
+    /// known last sale price in the currency of the royalty are charged `fallback_percent` of the
+    /// whole payment instead.
+    ProfitShare {
+        percent: U256,
+        fallback_percent: U256,
+    },
 }
 
 serializable_structs! {
+    /// A percentage that applies from block time `from` until `to`, or indefinitely if `to` is
+    /// `None`.
     #[derive(Debug, Clone, PartialEq, Eq)]
-    pub struct RoyaltyStructure {
-        pub steps: Vec<RoyaltyStep>,
+    pub struct RoyaltyWindow {
+        pub from: u64,
+        pub to: Option<u64>,
+        pub percent: U256,
+    }
+}
+
+// Unlike royalty windows, which are only serialized within the fields of a royalty step, the
+// structures below are returned by views, so they have a CL type. It is implemented along with
+// their serialization in `bytes.rs`, like that of royalty steps and payment states.
+
+/// A recipient of royalties, with its `share` of every royalty in basis points. Recipients claim
+/// their royalties themselves, so they must be accounts or contract packages (as [`Key::Hash`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoyaltyRecipient {
+    pub recipient: Key,
+    pub share: u32,
+}
+
+/// The royalty steps are applied in order to compute the royalty of a sale, which is then split
+/// between the `recipients`. If there are no recipients, the whole royalty is left to be withdrawn
+/// by the manager. All amounts are denominated in the `currency` of the structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoyaltyStructure {
+    pub steps: Vec<RoyaltyStep>,
+    pub recipients: Vec<RoyaltyRecipient>,
+    pub currency: Currency,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Withdrawal {
+    pub amount: U512,
+    pub currency: Currency,
+    pub target: Key,
+    pub block_time: u64,
+}
+
+/// Running totals of the royalty payments made in a single currency, either for a collection or
+/// through a marketplace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoyaltyStats {
+    pub payment_count: u64,
+    pub sale_volume: U512,
+    pub royalty_amount: U512,
+}
+
+/// Breakdown of the royalty owed on a payment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoyaltyQuote {
+    pub payment_amount: U512,
+    /// The royalty accumulated after each step of the royalty structure, capped at the payment
+    /// like the total. The total is the last of them, raised to the minimum if there is one.
+    pub steps: Vec<U512>,
+    pub total_royalty: U512,
+    /// The share of the royalty of each recipient.
+    pub splits: Vec<(Key, U512)>,
+    /// The royalty as a percentage of the payment, in basis points.
+    pub effective_rate: U256,
+    pub currency: Currency,
+}
+
+/// A royalty payment whose transfer went through, kept as the provenance of the token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettledPayment {
+    pub payer: Key,
+    pub source_key: Key,
+    pub target_key: Key,
+    pub marketplace: Key,
+    pub sale_price: U512,
+    pub amount: U512,
+    pub currency: Currency,
+    pub block_time: u64,
+}
+
+/// The circumstances of a sale that royalties may depend on, besides the payment itself. Quotes
+/// and charges made in the same block see the same context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaleContext {
+    pub block_time: u64,
+    /// Price of the last settled sale of the token, if it was paid in the currency of the royalty.
+    pub last_sale_price: Option<U512>,
+}
+
+impl RoyaltyWindow {
+    pub fn contains(&self, block_time: u64) -> bool {
+        self.from <= block_time && self.to.map_or(true, |to| block_time < to)
     }
 }
 
 impl RoyaltyStructure {
-    pub fn calculate_total_royalty(&self, total_payment: U512) -> U512 {
-        let mut payment = total_payment;
-        let mut total_royalty = U512::zero();
+    /// Reverts if the structure cannot be applied to a sale, or could charge more than the whole
+    /// payment.
+    ///
+    /// The percentages that apply to any part of a payment may add up to at most 100%, so that
+    /// percentage based royalties never exceed the payment. Flat amounts cannot be checked against
+    /// a payment in advance, see [`Self::calculate_total_royalty`].
+    pub fn validate(&self) {
+        let mut minimum_seen = false;
+        let mut flat_total = U512::zero();
         for step in &self.steps {
             match step {
-                RoyaltyStep::Minimum { amount } => {
-                    if payment < *amount {
-                        payment = *amount;
+                RoyaltyStep::Minimum { .. } => {
+                    ensure!(!minimum_seen, CustodialError::DuplicateMinimumStep);
+                    minimum_seen = true;
+                }
+                RoyaltyStep::Flat { amount } => {
+                    flat_total = o_unwrap!(
+                        flat_total.checked_add(*amount),
+                        CustodialError::FlatAmountsOverflow
+                    );
+                }
+                RoyaltyStep::Percentage { percent } => {
+                    ensure!(
+                        *percent <= U256::from(BASIS_POINTS),
+                        CustodialError::PercentageOutOfRange
+                    );
+                }
+                RoyaltyStep::Bracket { from, to, percent } => {
+                    ensure!(
+                        *percent <= U256::from(BASIS_POINTS),
+                        CustodialError::PercentageOutOfRange
+                    );
+                    if let Some(to) = to {
+                        ensure!(to > from, CustodialError::InvalidBracket);
+                    }
+                }
+                RoyaltyStep::ProfitShare {
+                    percent,
+                    fallback_percent,
+                } => {
+                    ensure!(
+                        *percent <= U256::from(BASIS_POINTS)
+                            && *fallback_percent <= U256::from(BASIS_POINTS),
+                        CustodialError::PercentageOutOfRange
+                    );
+                }
+                RoyaltyStep::Scheduled { windows } => {
+                    for window in windows {
+                        ensure!(
+                            window.percent <= U256::from(BASIS_POINTS),
+                            CustodialError::PercentageOutOfRange
+                        );
+                        if let Some(to) = window.to {
+                            ensure!(to > window.from, CustodialError::InvalidRoyaltyWindow);
+                        }
                     }
                 }
+                RoyaltyStep::Maximum { .. } => {}
+            }
+        }
+
+        ensure!(
+            self.max_total_percentage() <= U256::from(BASIS_POINTS),
+            CustodialError::TotalPercentageOutOfRange
+        );
+
+        if let Some(currency) = self.currency {
+            ensure!(
+                is_currency_accepted(currency),
+                CustodialError::UnsupportedCurrency
+            );
+        }
+
+        self.validate_recipients();
+    }
+
+    fn validate_recipients(&self) {
+        if self.recipients.is_empty() {
+            return;
+        }
+
+        let mut total_share = 0u64;
+        for (i, recipient) in self.recipients.iter().enumerate() {
+            ensure!(
+                matches!(recipient.recipient, Key::Account(_) | Key::Hash(_)),
+                CustodialError::InvalidRecipient
+            );
+            ensure!(recipient.share > 0, CustodialError::InvalidRecipientShare);
+            ensure!(
+                !self.recipients[..i]
+                    .iter()
+                    .any(|other| other.recipient == recipient.recipient),
+                CustodialError::DuplicateRecipient
+            );
+
+            total_share += u64::from(recipient.share);
+        }
+
+        ensure!(
+            total_share == BASIS_POINTS,
+            CustodialError::InvalidRecipientShare
+        );
+    }
+
+    // The highest combined percentage that applies to any single part of a payment. Percentage
+    // steps apply to the whole payment, brackets only to their own range, so the combined
+    // percentage can only increase where a bracket starts.
+    fn max_total_percentage(&self) -> U256 {
+        let mut base = U256::zero();
+        let mut brackets = Vec::new();
+        for step in &self.steps {
+            match step {
+                RoyaltyStep::Percentage { percent } => base = base.saturating_add(*percent),
+                RoyaltyStep::ProfitShare {
+                    percent,
+                    fallback_percent,
+                } => base = base.saturating_add((*percent).max(*fallback_percent)),
+                RoyaltyStep::Scheduled { windows } => {
+                    let highest = windows.iter().map(|window| window.percent).max();
+                    base = base.saturating_add(highest.unwrap_or_default());
+                }
+                RoyaltyStep::Bracket { from, to, percent } => brackets.push((*from, *to, *percent)),
+                _ => {}
+            }
+        }
+
+        let bracket_total_at = |point: U512| {
+            brackets
+                .iter()
+                .filter(|(from, to, _)| *from <= point && to.map_or(true, |to| point < to))
+                .fold(U256::zero(), |total, (_, _, percent)| {
+                    total.saturating_add(*percent)
+                })
+        };
+
+        brackets
+            .iter()
+            .map(|(from, _, _)| bracket_total_at(*from))
+            .max()
+            .unwrap_or_default()
+            .saturating_add(base)
+    }
+
+    /// Split `royalty` between the recipients according to their shares. The remainder left by
+    /// rounding goes to the first recipient.
+    pub fn split(&self, royalty: U512) -> Vec<(Key, U512)> {
+        let mut splits: Vec<(Key, U512)> = self
+            .recipients
+            .iter()
+            .map(|recipient| {
+                let amount = royalty
+                    .checked_mul(recipient.share.into())
+                    .unwrap_or_revert_with(CustodialError::Overflow)
+                    / U512::from(BASIS_POINTS);
+                (recipient.recipient, amount)
+            })
+            .collect();
+
+        let allocated = splits
+            .iter()
+            .fold(U512::zero(), |total, (_, amount)| total + *amount);
+        if let Some((_, amount)) = splits.first_mut() {
+            *amount += royalty - allocated;
+        }
+
+        splits
+    }
+
+    /// Royalty owed on a payment of `total_payment`, which it never exceeds.
+    pub fn calculate_total_royalty(&self, total_payment: U512, sale: &SaleContext) -> U512 {
+        self.calculate_itemized_royalty(total_payment, sale).1
+    }
+
+    /// Itemized quote of the royalty owed on a payment of `total_payment`.
+    pub fn quote(&self, total_payment: U512, sale: &SaleContext) -> RoyaltyQuote {
+        let (steps, total_royalty) = self.calculate_itemized_royalty(total_payment, sale);
+        let effective_rate = if total_payment.is_zero() {
+            U256::zero()
+        } else {
+            (total_royalty * U512::from(BASIS_POINTS) / total_payment).as_()
+        };
+
+        RoyaltyQuote {
+            payment_amount: total_payment,
+            steps,
+            total_royalty,
+            splits: self.split(total_royalty),
+            effective_rate,
+            currency: self.currency,
+        }
+    }
+
+    // Returns the royalty accumulated after each step, along with the total royalty.
+    fn calculate_itemized_royalty(
+        &self,
+        total_payment: U512,
+        sale: &SaleContext,
+    ) -> (Vec<U512>, U512) {
+        let mut minimum = U512::zero();
+        let mut total_royalty = U512::zero();
+        let mut steps = Vec::with_capacity(self.steps.len());
+        for step in &self.steps {
+            match step {
+                RoyaltyStep::Minimum { amount } => minimum = *amount,
                 RoyaltyStep::Flat { amount } => {
                     total_royalty = total_royalty
                         .checked_add(*amount)
@@ -61,20 +890,57 @@ impl RoyaltyStructure {
                 }
                 RoyaltyStep::Percentage { percent } => {
                     total_royalty = total_royalty
-                        .checked_add(
-                            total_payment
-                                .checked_mul(percent.as_())
-                                .unwrap_or_revert_with(CustodialError::Overflow)
-                                .checked_div(10000u64.into())
-                                .unwrap_or_revert_with(CustodialError::Overflow),
-                        )
+                        .checked_add(percentage_of(total_payment, *percent))
+                        .unwrap_or_revert_with(CustodialError::Overflow);
+                }
+                RoyaltyStep::Bracket { from, to, percent } => {
+                    let upper = to.map_or(total_payment, |to| total_payment.min(to));
+                    total_royalty = total_royalty
+                        .checked_add(percentage_of(upper.saturating_sub(*from), *percent))
+                        .unwrap_or_revert_with(CustodialError::Overflow);
+                }
+                RoyaltyStep::Scheduled { windows } => {
+                    let percent = windows
+                        .iter()
+                        .find(|window| window.contains(sale.block_time))
+                        .map_or(U256::zero(), |window| window.percent);
+                    total_royalty = total_royalty
+                        .checked_add(percentage_of(total_payment, percent))
                         .unwrap_or_revert_with(CustodialError::Overflow);
                 }
+                RoyaltyStep::ProfitShare {
+                    percent,
+                    fallback_percent,
+                } => {
+                    let royalty = match sale.last_sale_price {
+                        Some(last_sale_price) => {
+                            percentage_of(total_payment.saturating_sub(last_sale_price), *percent)
+                        }
+                        None => percentage_of(total_payment, *fallback_percent),
+                    };
+                    total_royalty = total_royalty
+                        .checked_add(royalty)
+                        .unwrap_or_revert_with(CustodialError::Overflow);
+                }
+                RoyaltyStep::Maximum { amount } => {
+                    total_royalty = total_royalty.min(*amount);
+                }
             }
+
+            // Flat amounts can still exceed a small payment, so steps are capped like the total.
+            steps.push(total_royalty.min(total_payment));
         }
 
-        total_royalty
+        (steps, total_royalty.max(minimum).min(total_payment))
     }
 }
 
+fn percentage_of(amount: U512, percent: U256) -> U512 {
+    amount
+        .checked_mul(percent.as_())
+        .unwrap_or_revert_with(CustodialError::Overflow)
+        .checked_div(BASIS_POINTS.into())
+        .unwrap_or_revert_with(CustodialError::Overflow)
+}
+
 impl FromNamedArg for RoyaltyStructure {}
@@ -73,6 +73,7 @@ pub fn bid(
             entry.token_contract,
             &entry.token_id,
             entry.price,
+            source_key,
         );
 
         let owned_purse = casper_contract::contract_api::system::create_purse();
@@ -0,0 +1,96 @@
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+use casper_contract::contract_api::{account, runtime, system};
+use casper_contract::unwrap_or_revert::UnwrapOrRevert;
+use casper_types::{runtime_args, ContractPackageHash, Key, RuntimeArgs, U512};
+
+pub const CUSTODIAL_PACKAGE_HASH: &str = "custodial_package_hash";
+pub const TOKEN_CONTRACT: &str = "token_contract";
+pub const TOKEN_ID: &str = "token_id";
+pub const SOURCE_KEY: &str = "source_key";
+pub const TARGET_KEY: &str = "target_key";
+pub const SOURCE_PURSE: &str = "source_purse";
+pub const PAYMENT_AMOUNT: &str = "payment_amount";
+pub const CALCULATE_ROYALTY_ENTRYPOINT: &str = "calculate_royalty";
+pub const GET_ROYALTY_CURRENCY_ENTRYPOINT: &str = "get_royalty_currency";
+pub const PAY_ROYALTY_DIRECT_ENTRYPOINT: &str = "pay_royalty_direct";
+pub const TRANSFER_ENTRYPOINT: &str = "transfer";
+
+// Sells a token of an ordinal CEP-78 collection without a marketplace: pays the royalty on the
+// declared `payment_amount` and transfers the token from the calling account to `target_key`.
+// Royalties priced in a CEP-18 token must have been approved to the custodial contract beforehand.
+#[no_mangle]
+pub extern "C" fn call() {
+    let custodial_package_hash: ContractPackageHash =
+        runtime::get_named_arg(CUSTODIAL_PACKAGE_HASH);
+    let token_contract: ContractPackageHash = runtime::get_named_arg(TOKEN_CONTRACT);
+    let token_id: u64 = runtime::get_named_arg(TOKEN_ID);
+    let target_key: Key = runtime::get_named_arg(TARGET_KEY);
+    let payment_amount: U512 = runtime::get_named_arg(PAYMENT_AMOUNT);
+    let source_key = Key::Account(runtime::get_caller());
+
+    let currency: Option<ContractPackageHash> = runtime::call_versioned_contract(
+        custodial_package_hash,
+        None,
+        GET_ROYALTY_CURRENCY_ENTRYPOINT,
+        runtime_args! {
+            TOKEN_CONTRACT => token_contract,
+            TOKEN_ID => token_id,
+            SOURCE_KEY => source_key
+        },
+    );
+
+    let mut args = runtime_args! {
+        TOKEN_CONTRACT => token_contract,
+        TOKEN_ID => token_id,
+        TARGET_KEY => target_key,
+        PAYMENT_AMOUNT => payment_amount
+    };
+
+    if currency.is_none() {
+        let royalty: U512 = runtime::call_versioned_contract(
+            custodial_package_hash,
+            None,
+            CALCULATE_ROYALTY_ENTRYPOINT,
+            runtime_args! {
+                TOKEN_CONTRACT => token_contract,
+                TOKEN_ID => token_id,
+                PAYMENT_AMOUNT => payment_amount,
+                SOURCE_KEY => source_key
+            },
+        );
+
+        let owned_purse = system::create_purse();
+
+        system::transfer_from_purse_to_purse(
+            account::get_main_purse(),
+            owned_purse,
+            royalty,
+            None
+        ).unwrap_or_revert();
+
+        args.insert(SOURCE_PURSE, owned_purse).unwrap_or_revert();
+    }
+
+    let _: () = runtime::call_versioned_contract(
+        custodial_package_hash,
+        None,
+        PAY_ROYALTY_DIRECT_ENTRYPOINT,
+        args,
+    );
+
+    let _: (String, Key) = runtime::call_versioned_contract(
+        token_contract,
+        None,
+        TRANSFER_ENTRYPOINT,
+        runtime_args! {
+            TOKEN_ID => token_id,
+            SOURCE_KEY => source_key,
+            TARGET_KEY => target_key
+        },
+    );
+}
@@ -0,0 +1,50 @@
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{
+    bytesrepr::{self, Bytes, FromBytes},
+    CLType, CLTyped, ContractPackageHash, RuntimeArgs,
+};
+
+pub const CONTRACT_PACKAGE: &str = "contract_package";
+pub const ENTRY_POINT: &str = "entry_point";
+pub const ARGS: &str = "args";
+pub const VIEW_RESULT: &str = "view_result";
+
+// The serialized return value of a view, whatever its type.
+struct RawValue(Vec<u8>);
+
+impl CLTyped for RawValue {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl FromBytes for RawValue {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        Ok((RawValue(bytes.to_vec()), &[]))
+    }
+}
+
+// Calls `entry_point` of the latest version of `contract_package` with `args`, given as serialized
+// runtime arguments, and stores its serialized return value under the `view_result` named key of
+// the calling account, for tests to read.
+#[no_mangle]
+pub extern "C" fn call() {
+    let contract_package: ContractPackageHash = runtime::get_named_arg(CONTRACT_PACKAGE);
+    let entry_point: String = runtime::get_named_arg(ENTRY_POINT);
+    let args: Bytes = runtime::get_named_arg(ARGS);
+    let args: RuntimeArgs = bytesrepr::deserialize(args.to_vec()).unwrap_or_revert();
+
+    let RawValue(result) =
+        runtime::call_versioned_contract(contract_package, None, &entry_point, args);
+
+    runtime::put_key(VIEW_RESULT, storage::new_uref(Bytes::from(result)).into());
+}
@@ -0,0 +1,151 @@
+#![no_main]
+#![no_std]
+
+//! A stand-in for an ordinal CEP-78 collection with a transfer filter, holding just enough state
+//! for the custodial contract: the owner of each token. Nothing is ever approved, so royalties can
+//! only be paid directly by the owners.
+
+extern crate alloc;
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+};
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{
+    runtime_args, ApiError, CLType, CLTyped, CLValue, ContractHash, EntryPoint, EntryPointAccess,
+    EntryPointType, EntryPoints, Key, Parameter, RuntimeArgs,
+};
+
+pub const NK_CONTRACT: &str = "mock_cep78";
+pub const NK_PACKAGE: &str = "mock_cep78_package";
+pub const TRANSFER_FILTER: &str = "transfer_filter";
+pub const TOKEN_ID: &str = "token_id";
+pub const OWNER: &str = "owner";
+pub const SOURCE_KEY: &str = "source_key";
+pub const TARGET_KEY: &str = "target_key";
+
+/// Reverted with when the transfer filter denies a transfer.
+pub const TRANSFER_DENIED: u16 = 1;
+
+const TRANSFER_PROCEED: u8 = 1;
+
+fn owner_uref(token_id: u64) -> Option<casper_types::URef> {
+    runtime::get_key(&format!("owner_{token_id}")).and_then(|key| key.into_uref())
+}
+
+#[no_mangle]
+pub extern "C" fn mint() {
+    let token_id: u64 = runtime::get_named_arg(TOKEN_ID);
+    let owner: Key = runtime::get_named_arg(OWNER);
+
+    if owner_uref(token_id).is_some() {
+        runtime::revert(ApiError::InvalidArgument);
+    }
+    runtime::put_key(
+        &format!("owner_{token_id}"),
+        storage::new_uref(owner).into(),
+    );
+}
+
+#[no_mangle]
+pub extern "C" fn owner_of() {
+    let token_id: u64 = runtime::get_named_arg(TOKEN_ID);
+    let owner: Key = storage::read(owner_uref(token_id).unwrap_or_revert())
+        .unwrap_or_revert()
+        .unwrap_or_revert();
+
+    runtime::ret(CLValue::from_t(owner).unwrap_or_revert());
+}
+
+#[no_mangle]
+pub extern "C" fn get_approved() {
+    runtime::ret(CLValue::from_t(Option::<Key>::None).unwrap_or_revert());
+}
+
+#[no_mangle]
+pub extern "C" fn transfer() {
+    let token_id: u64 = runtime::get_named_arg(TOKEN_ID);
+    let source_key: Key = runtime::get_named_arg(SOURCE_KEY);
+    let target_key: Key = runtime::get_named_arg(TARGET_KEY);
+
+    let transfer_filter = runtime::get_key(TRANSFER_FILTER)
+        .and_then(|key| key.into_hash())
+        .map(ContractHash::new)
+        .unwrap_or_revert();
+    let result: u8 = runtime::call_contract(
+        transfer_filter,
+        "can_transfer",
+        runtime_args! {
+            TOKEN_ID => token_id,
+            SOURCE_KEY => source_key,
+            TARGET_KEY => target_key
+        },
+    );
+    if result != TRANSFER_PROCEED {
+        runtime::revert(ApiError::User(TRANSFER_DENIED));
+    }
+
+    storage::write(owner_uref(token_id).unwrap_or_revert(), target_key);
+
+    runtime::ret(CLValue::from_t((String::new(), target_key)).unwrap_or_revert());
+}
+
+fn entry_point(name: &str, params: &[(&str, CLType)], ret: CLType) -> EntryPoint {
+    EntryPoint::new(
+        name,
+        params
+            .iter()
+            .map(|(name, cl_type)| Parameter::new(*name, cl_type.clone()))
+            .collect(),
+        ret,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn call() {
+    let transfer_filter: ContractHash = runtime::get_named_arg(TRANSFER_FILTER);
+
+    let mut entry_points = EntryPoints::new();
+    entry_points.add_entry_point(entry_point(
+        "mint",
+        &[(TOKEN_ID, CLType::U64), (OWNER, CLType::Key)],
+        CLType::Unit,
+    ));
+    entry_points.add_entry_point(entry_point(
+        "owner_of",
+        &[(TOKEN_ID, CLType::U64)],
+        CLType::Key,
+    ));
+    entry_points.add_entry_point(entry_point(
+        "get_approved",
+        &[(TOKEN_ID, CLType::U64)],
+        Option::<Key>::cl_type(),
+    ));
+    entry_points.add_entry_point(entry_point(
+        "transfer",
+        &[
+            (TOKEN_ID, CLType::U64),
+            (SOURCE_KEY, CLType::Key),
+            (TARGET_KEY, CLType::Key),
+        ],
+        <(String, Key)>::cl_type(),
+    ));
+
+    let mut named_keys = BTreeMap::new();
+    named_keys.insert(TRANSFER_FILTER.to_string(), Key::from(transfer_filter));
+
+    let (contract_hash, _) = storage::new_contract(
+        entry_points,
+        Some(named_keys),
+        Some(NK_PACKAGE.to_string()),
+        None,
+    );
+    runtime::put_key(NK_CONTRACT, contract_hash.into());
+}
@@ -0,0 +1,776 @@
+fn main() {
+    panic!("Execute \"cargo test\" to test the contracts instead of running this binary");
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_engine_test_support::{
+        ExecuteRequestBuilder, InMemoryWasmTestBuilder, DEFAULT_ACCOUNT_ADDR,
+        PRODUCTION_RUN_GENESIS_REQUEST,
+    };
+    use casper_execution_engine::core::{
+        engine_state::{Error as EngineStateError, ExecuteRequest},
+        execution::Error as ExecError,
+    };
+    use casper_types::{
+        account::AccountHash,
+        bytesrepr::{self, Bytes, FromBytes, ToBytes},
+        runtime_args, ApiError, CLType, CLValue, ContractHash, ContractPackageHash, Key,
+        RuntimeArgs, U256, U512,
+    };
+    use custodial::{
+        state::{
+            RoyaltyPaymentState, RoyaltyQuote, RoyaltyRecipient, RoyaltyStep, RoyaltyStructure,
+            RoyaltyWindow, DEFAULT_PAYMENT_EXPIRY,
+        },
+        CustodialError,
+    };
+
+    const CUSTODIAL_WASM: &str = "custodial.wasm";
+    const CUSTODIAL_BASELINE_WASM: &str = "custodial_baseline.wasm";
+    const CALL_VIEW_WASM: &str = "call_view.wasm";
+    const MOCK_CEP78_WASM: &str = "mock_cep78.wasm";
+
+    const MOCK_CEP78_CONTRACT: &str = "mock_cep78";
+    const MOCK_CEP78_PACKAGE: &str = "mock_cep78_package";
+    const MOCK_TRANSFER_DENIED: u16 = 1;
+    const VIEW_RESULT: &str = "view_result";
+
+    const TOKEN_ID: u64 = 1;
+    const PAYMENT_AMOUNT: u64 = 10_000;
+
+    fn cep18_royalty_structure() -> RoyaltyStructure {
+        RoyaltyStructure {
+            steps: vec![RoyaltyStep::Percentage {
+                percent: U256::from(500),
+            }],
+            recipients: Vec::new(),
+            currency: Some(ContractPackageHash::new([1u8; 32])),
+        }
+    }
+
+    fn royalty_structure(steps: Vec<RoyaltyStep>) -> RoyaltyStructure {
+        RoyaltyStructure {
+            steps,
+            recipients: Vec::new(),
+            currency: None,
+        }
+    }
+
+    fn percentage(percent: u64) -> RoyaltyStep {
+        RoyaltyStep::Percentage {
+            percent: U256::from(percent),
+        }
+    }
+
+    fn account(byte: u8) -> Key {
+        Key::Account(AccountHash::new([byte; 32]))
+    }
+
+    fn manager() -> Key {
+        Key::Account(*DEFAULT_ACCOUNT_ADDR)
+    }
+
+    fn setup() -> InMemoryWasmTestBuilder {
+        let mut builder = InMemoryWasmTestBuilder::default();
+        builder.run_genesis(&PRODUCTION_RUN_GENESIS_REQUEST);
+        builder
+    }
+
+    fn install_request(royalty_structure: RoyaltyStructure) -> ExecuteRequest {
+        ExecuteRequestBuilder::standard(
+            *DEFAULT_ACCOUNT_ADDR,
+            CUSTODIAL_WASM,
+            runtime_args! {
+                "whitelisted_marketplaces" => Vec::<ContractPackageHash>::new(),
+                "royalty_structure" => royalty_structure,
+                "manager" => manager(),
+            },
+        )
+        .build()
+    }
+
+    fn install(builder: &mut InMemoryWasmTestBuilder, royalty_structure: RoyaltyStructure) {
+        builder
+            .exec(install_request(royalty_structure))
+            .expect_success()
+            .commit();
+    }
+
+    // The hash stored under `name` by the default account.
+    fn account_named_key(builder: &InMemoryWasmTestBuilder, name: &str) -> [u8; 32] {
+        builder
+            .get_expected_account(*DEFAULT_ACCOUNT_ADDR)
+            .named_keys()
+            .get(name)
+            .and_then(|key| key.into_hash())
+            .unwrap_or_else(|| panic!("missing named key {name}"))
+    }
+
+    // The contract installed last under `custodial::NK_CONTRACT`.
+    fn custodial_contract(builder: &InMemoryWasmTestBuilder) -> ContractHash {
+        let named_keys = builder
+            .get_expected_account(*DEFAULT_ACCOUNT_ADDR)
+            .named_keys()
+            .clone();
+        let name = (1..)
+            .map(|i| format!("{}_{i}", custodial::NK_CONTRACT))
+            .take_while(|name| named_keys.contains_key(name))
+            .last()
+            .unwrap_or_else(|| custodial::NK_CONTRACT.to_string());
+
+        ContractHash::new(account_named_key(builder, &name))
+    }
+
+    fn custodial_package(builder: &InMemoryWasmTestBuilder) -> ContractPackageHash {
+        builder
+            .get_contract(custodial_contract(builder))
+            .expect("custodial contract should exist")
+            .contract_package_hash()
+    }
+
+    fn custodial_call(
+        builder: &InMemoryWasmTestBuilder,
+        entry_point: &str,
+        args: RuntimeArgs,
+    ) -> ExecuteRequestBuilder {
+        ExecuteRequestBuilder::versioned_contract_call_by_hash(
+            *DEFAULT_ACCOUNT_ADDR,
+            custodial_package(builder),
+            None,
+            entry_point,
+            args,
+        )
+    }
+
+    fn exec_custodial(builder: &mut InMemoryWasmTestBuilder, entry_point: &str, args: RuntimeArgs) {
+        let request = custodial_call(builder, entry_point, args).build();
+        builder.exec(request).expect_success().commit();
+    }
+
+    fn expect_error(
+        builder: &mut InMemoryWasmTestBuilder,
+        request: ExecuteRequest,
+        expected: ApiError,
+    ) {
+        builder.exec(request).expect_failure().commit();
+
+        let error = builder.get_error().expect("request should have failed");
+        let reverted_with = match &error {
+            EngineStateError::Exec(ExecError::Revert(api_error)) => Some(*api_error),
+            _ => None,
+        };
+        assert_eq!(reverted_with, Some(expected), "{error:?}");
+    }
+
+    fn expect_custodial_error(
+        builder: &mut InMemoryWasmTestBuilder,
+        entry_point: &str,
+        args: RuntimeArgs,
+        expected: CustodialError,
+    ) {
+        let request = custodial_call(builder, entry_point, args).build();
+        expect_error(builder, request, expected.into());
+    }
+
+    // Calls a view of the latest custodial contract at `block_time` through the `call_view`
+    // session, which stores the serialized result in the default account.
+    fn view_at<T: FromBytes>(
+        builder: &mut InMemoryWasmTestBuilder,
+        block_time: u64,
+        entry_point: &str,
+        args: RuntimeArgs,
+    ) -> T {
+        let request = ExecuteRequestBuilder::standard(
+            *DEFAULT_ACCOUNT_ADDR,
+            CALL_VIEW_WASM,
+            runtime_args! {
+                "contract_package" => custodial_package(builder),
+                "entry_point" => entry_point.to_string(),
+                "args" => Bytes::from(args.to_bytes().unwrap()),
+            },
+        )
+        .with_block_time(block_time)
+        .build();
+        builder.exec(request).expect_success().commit();
+
+        let result: Bytes = builder
+            .query(
+                None,
+                Key::Account(*DEFAULT_ACCOUNT_ADDR),
+                &[VIEW_RESULT.to_string()],
+            )
+            .expect("view result should be stored")
+            .as_cl_value()
+            .cloned()
+            .expect("view result should be a CL value")
+            .into_t()
+            .expect("view result should be bytes");
+
+        bytesrepr::deserialize(result.to_vec()).expect("view result should deserialize")
+    }
+
+    fn view<T: FromBytes>(
+        builder: &mut InMemoryWasmTestBuilder,
+        entry_point: &str,
+        args: RuntimeArgs,
+    ) -> T {
+        view_at(builder, 0, entry_point, args)
+    }
+
+    fn set_royalty_structure(builder: &mut InMemoryWasmTestBuilder, steps: Vec<RoyaltyStep>) {
+        exec_custodial(
+            builder,
+            "set_royalty_structure",
+            runtime_args! {
+                "royalty_structure" => royalty_structure(steps),
+            },
+        );
+    }
+
+    fn calculate_royalty_at(builder: &mut InMemoryWasmTestBuilder, block_time: u64) -> U512 {
+        view_at(
+            builder,
+            block_time,
+            "calculate_royalty",
+            runtime_args! {
+                "token_contract" => ContractPackageHash::new([9u8; 32]),
+                "token_id" => TOKEN_ID,
+                "payment_amount" => U512::from(PAYMENT_AMOUNT),
+            },
+        )
+    }
+
+    fn calculate_royalty(builder: &mut InMemoryWasmTestBuilder) -> U512 {
+        calculate_royalty_at(builder, 0)
+    }
+
+    #[test]
+    fn should_install_with_cep18_royalty_structure() {
+        let mut builder = setup();
+        install(&mut builder, cep18_royalty_structure());
+
+        // The install-time currency stays accepted once the contract is installed.
+        let set_structure_request = ExecuteRequestBuilder::contract_call_by_name(
+            *DEFAULT_ACCOUNT_ADDR,
+            custodial::NK_CONTRACT,
+            "set_royalty_structure",
+            runtime_args! {
+                "royalty_structure" => cep18_royalty_structure(),
+            },
+        )
+        .build();
+        builder
+            .exec(set_structure_request)
+            .expect_success()
+            .commit();
+    }
+
+    #[test]
+    fn should_reject_invalid_royalty_structures() {
+        let mut builder = setup();
+
+        // Misconfigured structures already fail at install.
+        let request = install_request(royalty_structure(vec![percentage(10_001)]));
+        expect_error(
+            &mut builder,
+            request,
+            CustodialError::PercentageOutOfRange.into(),
+        );
+
+        install(&mut builder, royalty_structure(vec![percentage(500)]));
+
+        let invalid_structures = [
+            (
+                royalty_structure(vec![percentage(10_001)]),
+                CustodialError::PercentageOutOfRange,
+            ),
+            (
+                royalty_structure(vec![
+                    RoyaltyStep::Minimum {
+                        amount: U512::from(100),
+                    },
+                    percentage(500),
+                    RoyaltyStep::Minimum {
+                        amount: U512::from(200),
+                    },
+                ]),
+                CustodialError::DuplicateMinimumStep,
+            ),
+            (
+                royalty_structure(vec![percentage(6_000), percentage(5_000)]),
+                CustodialError::TotalPercentageOutOfRange,
+            ),
+            (
+                RoyaltyStructure {
+                    steps: vec![percentage(500)],
+                    recipients: vec![
+                        RoyaltyRecipient {
+                            recipient: account(2),
+                            share: 5_000,
+                        },
+                        RoyaltyRecipient {
+                            recipient: account(3),
+                            share: 4_000,
+                        },
+                    ],
+                    currency: None,
+                },
+                CustodialError::InvalidRecipientShare,
+            ),
+        ];
+
+        for (royalty_structure, error) in invalid_structures {
+            expect_custodial_error(
+                &mut builder,
+                "set_royalty_structure",
+                runtime_args! {
+                    "royalty_structure" => royalty_structure,
+                },
+                error,
+            );
+        }
+    }
+
+    #[test]
+    fn should_charge_every_step_type() {
+        let mut builder = setup();
+        install(&mut builder, royalty_structure(vec![percentage(500)]));
+
+        let charges = [
+            (
+                vec![RoyaltyStep::Flat {
+                    amount: U512::from(300),
+                }],
+                300,
+            ),
+            (vec![percentage(500)], 500),
+            (
+                vec![
+                    percentage(500),
+                    RoyaltyStep::Minimum {
+                        amount: U512::from(700),
+                    },
+                ],
+                700,
+            ),
+            (
+                vec![RoyaltyStep::Bracket {
+                    from: U512::from(5_000),
+                    to: None,
+                    percent: U256::from(1_000),
+                }],
+                500,
+            ),
+            (
+                vec![
+                    percentage(5_000),
+                    RoyaltyStep::Maximum {
+                        amount: U512::from(2_000),
+                    },
+                ],
+                2_000,
+            ),
+            // Tokens without a last sale price are charged the fallback percentage.
+            (
+                vec![RoyaltyStep::ProfitShare {
+                    percent: U256::from(2_000),
+                    fallback_percent: U256::from(300),
+                }],
+                300,
+            ),
+        ];
+
+        for (steps, royalty) in charges {
+            set_royalty_structure(&mut builder, steps.clone());
+            assert_eq!(
+                calculate_royalty(&mut builder),
+                U512::from(royalty),
+                "{steps:?}"
+            );
+        }
+
+        set_royalty_structure(
+            &mut builder,
+            vec![RoyaltyStep::Scheduled {
+                windows: vec![
+                    RoyaltyWindow {
+                        from: 0,
+                        to: Some(1_000),
+                        percent: U256::from(1_000),
+                    },
+                    RoyaltyWindow {
+                        from: 1_000,
+                        to: Some(2_000),
+                        percent: U256::from(200),
+                    },
+                ],
+            }],
+        );
+        assert_eq!(calculate_royalty_at(&mut builder, 500), U512::from(1_000));
+        assert_eq!(calculate_royalty_at(&mut builder, 1_500), U512::from(200));
+        assert_eq!(calculate_royalty_at(&mut builder, 2_500), U512::zero());
+    }
+
+    #[test]
+    fn should_cap_royalty_at_payment() {
+        let mut builder = setup();
+        install(&mut builder, royalty_structure(vec![percentage(500)]));
+
+        set_royalty_structure(
+            &mut builder,
+            vec![
+                RoyaltyStep::Flat {
+                    amount: U512::from(PAYMENT_AMOUNT * 2),
+                },
+                percentage(500),
+            ],
+        );
+        assert_eq!(calculate_royalty(&mut builder), U512::from(PAYMENT_AMOUNT));
+
+        set_royalty_structure(
+            &mut builder,
+            vec![RoyaltyStep::Minimum {
+                amount: U512::from(PAYMENT_AMOUNT * 3),
+            }],
+        );
+        assert_eq!(calculate_royalty(&mut builder), U512::from(PAYMENT_AMOUNT));
+    }
+
+    #[test]
+    fn should_give_split_remainder_to_first_recipient() {
+        let mut builder = setup();
+        install(
+            &mut builder,
+            RoyaltyStructure {
+                steps: vec![RoyaltyStep::Flat {
+                    amount: U512::from(100),
+                }],
+                recipients: vec![
+                    RoyaltyRecipient {
+                        recipient: account(2),
+                        share: 3_333,
+                    },
+                    RoyaltyRecipient {
+                        recipient: account(3),
+                        share: 3_333,
+                    },
+                    RoyaltyRecipient {
+                        recipient: account(4),
+                        share: 3_334,
+                    },
+                ],
+                currency: None,
+            },
+        );
+
+        let quote: RoyaltyQuote = view(
+            &mut builder,
+            "quote_royalty",
+            runtime_args! {
+                "token_contract" => ContractPackageHash::new([9u8; 32]),
+                "token_id" => TOKEN_ID,
+                "payment_amount" => U512::from(PAYMENT_AMOUNT),
+            },
+        );
+
+        assert_eq!(quote.total_royalty, U512::from(100));
+        assert_eq!(
+            quote.splits,
+            vec![
+                (account(2), U512::from(34)),
+                (account(3), U512::from(33)),
+                (account(4), U512::from(33)),
+            ]
+        );
+    }
+
+    // Installs the custodial contract charging 10% and a mock collection filtered by it, with a
+    // token owned by the default account. Returns the package of the collection.
+    fn setup_collection(builder: &mut InMemoryWasmTestBuilder) -> ContractPackageHash {
+        install(builder, royalty_structure(vec![percentage(1_000)]));
+
+        let transfer_filter =
+            ContractHash::new(account_named_key(builder, custodial::NK_TRANSFER_FILTER));
+        let request = ExecuteRequestBuilder::standard(
+            *DEFAULT_ACCOUNT_ADDR,
+            MOCK_CEP78_WASM,
+            runtime_args! {
+                "transfer_filter" => transfer_filter,
+            },
+        )
+        .build();
+        builder.exec(request).expect_success().commit();
+
+        let request = ExecuteRequestBuilder::contract_call_by_name(
+            *DEFAULT_ACCOUNT_ADDR,
+            MOCK_CEP78_CONTRACT,
+            "mint",
+            runtime_args! {
+                "token_id" => TOKEN_ID,
+                "owner" => manager(),
+            },
+        )
+        .build();
+        builder.exec(request).expect_success().commit();
+
+        ContractPackageHash::new(account_named_key(builder, MOCK_CEP78_PACKAGE))
+    }
+
+    fn pay_royalty_direct_request(
+        builder: &InMemoryWasmTestBuilder,
+        token_contract: ContractPackageHash,
+        target_key: Key,
+        block_time: u64,
+    ) -> ExecuteRequest {
+        let source_purse = builder
+            .get_expected_account(*DEFAULT_ACCOUNT_ADDR)
+            .main_purse();
+
+        custodial_call(
+            builder,
+            "pay_royalty_direct",
+            runtime_args! {
+                "token_contract" => token_contract,
+                "token_id" => TOKEN_ID,
+                "source_purse" => source_purse,
+                "target_key" => target_key,
+                "payment_amount" => U512::from(PAYMENT_AMOUNT),
+            },
+        )
+        .with_block_time(block_time)
+        .build()
+    }
+
+    fn transfer_request(target_key: Key) -> ExecuteRequest {
+        ExecuteRequestBuilder::contract_call_by_name(
+            *DEFAULT_ACCOUNT_ADDR,
+            MOCK_CEP78_CONTRACT,
+            "transfer",
+            runtime_args! {
+                "token_id" => TOKEN_ID,
+                "source_key" => manager(),
+                "target_key" => target_key,
+            },
+        )
+        .build()
+    }
+
+    fn royalty_purse_balance(builder: &InMemoryWasmTestBuilder) -> U512 {
+        let royalty_purse = builder
+            .get_contract(custodial_contract(builder))
+            .expect("custodial contract should exist")
+            .named_keys()
+            .get(custodial::NK_ROYALTY_PURSE)
+            .and_then(|key| key.into_uref())
+            .expect("royalty purse should exist");
+
+        builder.get_purse_balance(royalty_purse)
+    }
+
+    fn payment_state(
+        builder: &mut InMemoryWasmTestBuilder,
+        token_contract: ContractPackageHash,
+    ) -> RoyaltyPaymentState {
+        view(
+            builder,
+            "get_payment_state",
+            runtime_args! {
+                "token_contract" => token_contract,
+                "token_id" => TOKEN_ID,
+            },
+        )
+    }
+
+    #[test]
+    fn should_only_authorize_transfer_to_paid_target() {
+        let mut builder = setup();
+        let token_contract = setup_collection(&mut builder);
+        let buyer = account(2);
+
+        expect_error(
+            &mut builder,
+            transfer_request(buyer),
+            CustodialError::MustPayRoyalties.into(),
+        );
+
+        let request = pay_royalty_direct_request(&builder, token_contract, buyer, 0);
+        builder.exec(request).expect_success().commit();
+
+        expect_error(
+            &mut builder,
+            transfer_request(account(3)),
+            ApiError::User(MOCK_TRANSFER_DENIED),
+        );
+
+        builder
+            .exec(transfer_request(buyer))
+            .expect_success()
+            .commit();
+
+        assert_eq!(
+            payment_state(&mut builder, token_contract),
+            RoyaltyPaymentState::Unpaid
+        );
+        let settled_payment_count: u64 = view(
+            &mut builder,
+            "get_settled_payment_count",
+            runtime_args! {
+                "token_contract" => token_contract,
+                "token_id" => TOKEN_ID,
+            },
+        );
+        assert_eq!(settled_payment_count, 1);
+    }
+
+    #[test]
+    fn should_replace_and_refund_expired_payments() {
+        let mut builder = setup();
+        let token_contract = setup_collection(&mut builder);
+        let royalty = U512::from(PAYMENT_AMOUNT / 10);
+
+        let request = pay_royalty_direct_request(&builder, token_contract, account(2), 0);
+        builder.exec(request).expect_success().commit();
+        assert_eq!(royalty_purse_balance(&builder), royalty);
+
+        // A pending payment cannot be replaced by its source until it expires.
+        let request = pay_royalty_direct_request(&builder, token_contract, account(3), 0);
+        expect_error(&mut builder, request, CustodialError::AlreadyPaid.into());
+
+        // Once it has, the new payment replaces it, and it is refunded.
+        let request = pay_royalty_direct_request(
+            &builder,
+            token_contract,
+            account(3),
+            DEFAULT_PAYMENT_EXPIRY,
+        );
+        builder.exec(request).expect_success().commit();
+        assert_eq!(royalty_purse_balance(&builder), royalty);
+
+        let pending_payment = payment_state(&mut builder, token_contract);
+        let RoyaltyPaymentState::Paid { target_key, .. } = pending_payment else {
+            panic!("payment should be pending");
+        };
+        assert_eq!(target_key, account(3));
+
+        let refund_args = runtime_args! {
+            "token_contract" => token_contract,
+            "token_id" => TOKEN_ID,
+            "target" => manager(),
+        };
+        let request = custodial_call(&builder, "refund_royalty", refund_args.clone())
+            .with_block_time(DEFAULT_PAYMENT_EXPIRY)
+            .build();
+        expect_error(
+            &mut builder,
+            request,
+            CustodialError::PaymentNotExpired.into(),
+        );
+
+        let request = custodial_call(&builder, "refund_royalty", refund_args)
+            .with_block_time(DEFAULT_PAYMENT_EXPIRY * 2)
+            .build();
+        builder.exec(request).expect_success().commit();
+
+        assert_eq!(royalty_purse_balance(&builder), U512::zero());
+        assert_eq!(
+            payment_state(&mut builder, token_contract),
+            RoyaltyPaymentState::Unpaid
+        );
+    }
+
+    // The baseline contract stores its royalty structure as a list of steps, in a layout that
+    // tags flat and percentage steps with 1 and 2.
+    fn baseline_percentage_structure(percent: u64) -> CLValue {
+        let mut bytes = 1u32.to_bytes().unwrap();
+        bytes.push(2);
+        bytes.extend(U256::from(percent).to_bytes().unwrap());
+
+        CLValue::from_components(CLType::Any, bytes)
+    }
+
+    fn upgrade_request(builder: &InMemoryWasmTestBuilder) -> ExecuteRequest {
+        ExecuteRequestBuilder::standard(
+            *DEFAULT_ACCOUNT_ADDR,
+            CUSTODIAL_WASM,
+            runtime_args! {
+                "contract_package" => custodial_package(builder),
+                "previous_contract" => custodial_contract(builder),
+            },
+        )
+        .build()
+    }
+
+    #[test]
+    fn should_upgrade_baseline_contract() {
+        let mut builder = setup();
+
+        let mut install_args = runtime_args! {
+            "whitelisted_marketplaces" => Vec::<ContractPackageHash>::new(),
+            "manager" => manager(),
+        };
+        install_args.insert_cl_value("royalty_structure", baseline_percentage_structure(500));
+        let request = ExecuteRequestBuilder::standard(
+            *DEFAULT_ACCOUNT_ADDR,
+            CUSTODIAL_BASELINE_WASM,
+            install_args,
+        )
+        .build();
+        builder.exec(request).expect_success().commit();
+
+        let baseline_contract = custodial_contract(&builder);
+        let request = upgrade_request(&builder);
+        builder.exec(request).expect_success().commit();
+
+        // The structure is migrated from the baseline layout.
+        assert_eq!(calculate_royalty(&mut builder), U512::from(500));
+
+        // The contract gets a transfer filter, and the first sale rule is left off since the
+        // baseline did not record sales.
+        let transfer_filter: Option<ContractHash> =
+            view(&mut builder, "get_transfer_filter", RuntimeArgs::new());
+        assert_eq!(
+            transfer_filter,
+            Some(ContractHash::new(account_named_key(
+                &builder,
+                custodial::NK_TRANSFER_FILTER
+            )))
+        );
+        let first_sale_primary: bool =
+            view(&mut builder, "is_first_sale_primary", RuntimeArgs::new());
+        assert!(!first_sale_primary);
+
+        // The baseline version is disabled, and only the upgrade may migrate.
+        let request = ExecuteRequestBuilder::contract_call_by_hash(
+            *DEFAULT_ACCOUNT_ADDR,
+            baseline_contract,
+            "calculate_royalty",
+            runtime_args! {
+                "token_contract" => ContractPackageHash::new([9u8; 32]),
+                "token_id" => TOKEN_ID,
+                "payment_amount" => U512::from(PAYMENT_AMOUNT),
+            },
+        )
+        .build();
+        builder.exec(request).expect_failure().commit();
+
+        let request = custodial_call(
+            &builder,
+            "migrate",
+            runtime_args! {
+                "transfer_filter" => transfer_filter.unwrap(),
+            },
+        )
+        .build();
+        builder.exec(request).expect_failure().commit();
+
+        // Upgrading again reuses the upgrade group and the transfer filter.
+        let request = upgrade_request(&builder);
+        builder.exec(request).expect_success().commit();
+
+        let upgraded_transfer_filter: Option<ContractHash> =
+            view(&mut builder, "get_transfer_filter", RuntimeArgs::new());
+        assert_eq!(upgraded_transfer_filter, transfer_filter);
+        assert_eq!(calculate_royalty(&mut builder), U512::from(500));
+    }
+}